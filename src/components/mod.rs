@@ -1,2 +1,4 @@
 mod stack;
+mod storage;
 pub use stack::*;
+pub use storage::*;
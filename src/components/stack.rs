@@ -1,4 +1,4 @@
-use crate::evm::Stack;
+use evm_tetris::evm::Stack;
 use nes_yew::*;
 use yew::prelude::*;
 
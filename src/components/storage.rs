@@ -0,0 +1,45 @@
+use evm_tetris::evm::{Storage, TransientStorage};
+use nes_yew::*;
+use primitive_types::U256;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct StorageProps {
+    pub storage: Storage,
+    pub transient_storage: TransientStorage,
+}
+
+fn slots<'a>(slots: impl Iterator<Item = (&'a U256, &'a U256)>) -> Html {
+    let mut slots: Vec<_> = slots.collect();
+    slots.sort();
+    html! {
+        <List>
+            {
+                slots.into_iter().map(|(key, value)| {
+                    html! {
+                        <li>{ format!("{key}: {value}") }</li>
+                    }
+                }).collect::<Html>()
+            }
+        </List>
+    }
+}
+
+#[function_component(StorageViewer)]
+pub fn storage_viewer(
+    StorageProps {
+        storage,
+        transient_storage,
+    }: &StorageProps,
+) -> Html {
+    html! {
+        <div>
+            <Container title="Storage">
+                { slots(storage.iter()) }
+            </Container>
+            <Container title="Transient Storage">
+                { slots(transient_storage.iter()) }
+            </Container>
+        </div>
+    }
+}
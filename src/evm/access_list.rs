@@ -1,8 +1,31 @@
+use crate::evm::{precompile_addresses, Address, SpecId};
 use primitive_types::U256;
 use std::collections::HashSet;
 
+/// An entry of an EIP-2930 access list: an account and the storage slots
+/// of it to warm before the execution
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessListItem {
+    pub address: Address,
+    pub storage_keys: Vec<U256>,
+}
+
+/// An address or a storage slot warmed since the first open checkpoint
+#[derive(Debug, Clone)]
+enum Warmed {
+    Address(Address),
+    Slot(Address, U256),
+}
+
+/// The accounts and storage slots accessed so far, EIP-2929
+#[derive(Debug, Clone)]
 pub struct AccessList {
-    warm_slots: HashSet<U256>,
+    warm_addresses: HashSet<Address>,
+    warm_slots: HashSet<(Address, U256)>,
+    /// Addresses and slots warmed since the first open checkpoint
+    journal: Vec<Warmed>,
+    /// Length of the journal when each open checkpoint was taken
+    checkpoints: Vec<usize>,
 }
 
 impl Default for AccessList {
@@ -11,18 +34,169 @@ impl Default for AccessList {
     }
 }
 
+impl PartialEq for AccessList {
+    fn eq(&self, other: &Self) -> bool {
+        self.warm_addresses == other.warm_addresses && self.warm_slots == other.warm_slots
+    }
+}
+
+impl Eq for AccessList {}
+
 impl AccessList {
     pub fn new() -> Self {
         AccessList {
+            warm_addresses: HashSet::new(),
             warm_slots: HashSet::new(),
+            journal: Vec::new(),
+            checkpoints: Vec::new(),
+        }
+    }
+
+    /// Starts recording the addresses and slots warmed from now on,
+    /// so they can be made cold again with [`AccessList::revert_checkpoint`]
+    pub fn checkpoint(&mut self) {
+        self.checkpoints.push(self.journal.len());
+    }
+
+    /// Makes the addresses and slots warmed since the last checkpoint cold again and closes it
+    pub fn revert_checkpoint(&mut self) {
+        let journal_len = self
+            .checkpoints
+            .pop()
+            .expect("revert_checkpoint called without a checkpoint");
+        for warmed in self.journal.drain(journal_len..) {
+            match warmed {
+                Warmed::Address(address) => self.warm_addresses.remove(&address),
+                Warmed::Slot(address, slot) => self.warm_slots.remove(&(address, slot)),
+            };
         }
     }
 
-    pub fn add_warm_slot(&mut self, slot: U256) {
-        self.warm_slots.insert(slot);
+    /// Keeps the addresses and slots warmed since the last checkpoint and closes it,
+    /// they are made cold again with the enclosing checkpoint if any
+    pub fn discard_checkpoint(&mut self) {
+        self.checkpoints
+            .pop()
+            .expect("discard_checkpoint called without a checkpoint");
+        if self.checkpoints.is_empty() {
+            self.journal.clear();
+        }
+    }
+
+    /// Creates the access list a transaction starts with: the precompiles of `spec`,
+    /// `sender`, `recipient` and everything in the EIP-2930 `items` are warm.
+    pub fn for_transaction(
+        spec: SpecId,
+        sender: Address,
+        recipient: Address,
+        items: &[AccessListItem],
+    ) -> Self {
+        let mut access_list = AccessList::new();
+        for address in precompile_addresses(spec) {
+            access_list.add_warm_address(address);
+        }
+        access_list.add_warm_address(sender);
+        access_list.add_warm_address(recipient);
+        for item in items {
+            access_list.add_warm_address(item.address);
+            for &slot in &item.storage_keys {
+                access_list.add_warm_slot(item.address, slot);
+            }
+        }
+        access_list
+    }
+
+    /// Marks `address` as warm
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if `address` was cold.
+    pub fn add_warm_address(&mut self, address: Address) -> bool {
+        let cold = self.warm_addresses.insert(address);
+        if cold && !self.checkpoints.is_empty() {
+            self.journal.push(Warmed::Address(address));
+        }
+        cold
+    }
+
+    pub fn is_warm_address(&self, address: Address) -> bool {
+        self.warm_addresses.contains(&address)
+    }
+
+    /// Marks `slot` of `address` as warm
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the slot was cold.
+    pub fn add_warm_slot(&mut self, address: Address, slot: U256) -> bool {
+        let cold = self.warm_slots.insert((address, slot));
+        if cold && !self.checkpoints.is_empty() {
+            self.journal.push(Warmed::Slot(address, slot));
+        }
+        cold
+    }
+
+    pub fn is_warm_slot(&self, address: Address, slot: U256) -> bool {
+        self.warm_slots.contains(&(address, slot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::utils::address;
+
+    #[test]
+    fn test_warm_slots_per_address() {
+        let mut access_list = AccessList::new();
+        let (a, b) = (address(0xa), address(0xb));
+        assert!(access_list.add_warm_slot(a, U256::one()));
+        assert!(!access_list.add_warm_slot(a, U256::one()));
+        assert!(access_list.is_warm_slot(a, U256::one()));
+        assert!(!access_list.is_warm_slot(b, U256::one()));
+        // a warm slot does not warm its account
+        assert!(!access_list.is_warm_address(a));
+    }
+
+    #[test]
+    fn test_for_transaction() {
+        let (sender, recipient, listed) = (address(0x1000), address(0x2000), address(0x3000));
+        let access_list = AccessList::for_transaction(
+            SpecId::Cancun,
+            sender,
+            recipient,
+            &[AccessListItem {
+                address: listed,
+                storage_keys: vec![U256::zero(), U256::from(7)],
+            }],
+        );
+        for warm in [sender, recipient, listed, address(1)] {
+            assert!(access_list.is_warm_address(warm));
+        }
+        assert!(access_list.is_warm_address(address(9)));
+        assert!(!access_list.is_warm_address(address(10)));
+        assert!(access_list.is_warm_slot(listed, U256::from(7)));
+        assert!(!access_list.is_warm_slot(listed, U256::one()));
+        assert!(!access_list.is_warm_slot(recipient, U256::zero()));
     }
 
-    pub fn is_warm_slot(&self, slot: U256) -> bool {
-        self.warm_slots.contains(&slot)
+    #[test]
+    fn test_checkpoint() {
+        let (a, b) = (address(0xa), address(0xb));
+        let mut access_list = AccessList::new();
+        access_list.add_warm_address(a);
+        access_list.checkpoint();
+        access_list.add_warm_address(a);
+        access_list.add_warm_address(b);
+        access_list.checkpoint();
+        access_list.add_warm_slot(a, U256::one());
+        access_list.discard_checkpoint();
+        assert!(access_list.is_warm_slot(a, U256::one()));
+        access_list.revert_checkpoint();
+        // warm before the checkpoint
+        assert!(access_list.is_warm_address(a));
+        assert!(!access_list.is_warm_address(b));
+        assert!(!access_list.is_warm_slot(a, U256::one()));
+        assert!(access_list.journal.is_empty());
     }
 }
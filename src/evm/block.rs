@@ -0,0 +1,183 @@
+use crate::evm::{
+    execute_transaction, next_base_fee, BlockEnv, GasCost, InvalidTransaction, Receipt, SpecId,
+    Transaction, WorldState,
+};
+use primitive_types::U256;
+
+/// Runs the transactions of a block one after another against shared state
+#[derive(Debug, Clone)]
+pub struct BlockExecutor {
+    /// State left by the transactions executed so far
+    pub world: WorldState,
+    /// The block the transactions are included in
+    pub block: BlockEnv,
+    /// Fork whose rules apply
+    pub spec: SpecId,
+    /// Receipts of the included transactions, in order
+    pub receipts: Vec<Receipt>,
+    /// Gas used by the included transactions
+    gas_used: GasCost,
+}
+
+impl BlockExecutor {
+    pub fn new(world: WorldState, block: BlockEnv, spec: SpecId) -> Self {
+        BlockExecutor {
+            world,
+            block,
+            spec,
+            receipts: Vec::new(),
+            gas_used: GasCost::ZERO,
+        }
+    }
+
+    /// Returns the gas used by the included transactions
+    pub fn gas_used(&self) -> GasCost {
+        self.gas_used
+    }
+
+    /// Returns the gas left in the block
+    pub fn gas_left(&self) -> GasCost {
+        GasCost::from(self.block.gas_limit) - self.gas_used
+    }
+
+    /// Returns the base fee of the next block given the gas used so far, EIP-1559
+    pub fn next_base_fee(&self) -> U256 {
+        next_base_fee(self.block.base_fee, self.gas_used, self.block.gas_limit)
+    }
+
+    /// Executes `tx` on the state left by the earlier transactions.
+    ///
+    /// Every transaction starts with cold accounts and slots and empty transient storage,
+    /// and sees the storage written by the earlier ones as original values.
+    ///
+    /// # Returns
+    ///
+    /// Returns the receipt of `tx`.
+    /// When `tx` cannot be included, e.g. its gas limit is more than the gas left
+    /// in the block, returns `InvalidTransaction` error and nothing changes.
+    pub fn execute(&mut self, tx: &Transaction) -> Result<&Receipt, InvalidTransaction> {
+        let available = self.gas_left();
+        if GasCost::from(tx.gas_limit) > available {
+            return Err(InvalidTransaction::BlockGasLimitExceeded {
+                gas_limit: tx.gas_limit.into(),
+                available,
+            });
+        }
+        let receipt = execute_transaction(&mut self.world, &self.block, self.spec, tx)?;
+        self.world.commit();
+        self.world.clear_transient_storage();
+        self.gas_used += receipt.gas_used;
+        self.receipts.push(receipt);
+        Ok(self.receipts.last().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::utils::address;
+    use crate::evm::Bytecode;
+
+    const SENDER: u64 = 0x5e;
+    const CONTRACT: u64 = 0xc0;
+
+    /// An executor where `SENDER` holds 1 ether and `CONTRACT` runs `code`
+    fn executor(code: &str) -> BlockExecutor {
+        let mut world = WorldState::new();
+        world.account_mut(address(SENDER)).balance = U256::exp10(18);
+        let code = hex::decode(code).unwrap();
+        world.account_mut(address(CONTRACT)).code = Bytecode::from(&code[..]);
+        BlockExecutor::new(world, BlockEnv::default(), SpecId::Cancun)
+    }
+
+    /// A call from `SENDER` to `CONTRACT` with the word `n` as calldata
+    fn call(n: u64) -> Transaction {
+        let mut data = [0u8; 32];
+        U256::from(n).to_big_endian(&mut data);
+        Transaction {
+            caller: address(SENDER),
+            to: Some(address(CONTRACT)),
+            data: data.to_vec(),
+            gas_limit: 100_000,
+            ..Transaction::default()
+        }
+    }
+
+    /// Returns the gas used by `tx` beyond its intrinsic gas
+    fn execution_gas(executor: &mut BlockExecutor, tx: &Transaction) -> u64 {
+        let intrinsic = tx.intrinsic_gas(executor.spec).as_u64();
+        let receipt = executor.execute(tx).unwrap();
+        assert!(receipt.status);
+        (receipt.gas_used + receipt.gas_refunded).as_u64() - intrinsic
+    }
+
+    #[test]
+    fn test_original_values_persist() {
+        // stores the calldata in slot 0
+        let mut executor = executor("60003560005500");
+        let pushes = 3 * 3;
+        // 0 -> 1, a cold set
+        assert_eq!(execution_gas(&mut executor, &call(1)), pushes + 22_100);
+        // 1 -> 2, the slot is cold again and its original value is now 1
+        assert_eq!(execution_gas(&mut executor, &call(2)), pushes + 5000);
+        // 2 -> 0, a clear
+        assert_eq!(execution_gas(&mut executor, &call(0)), pushes + 5000);
+        assert_eq!(executor.receipts[2].gas_refunded, GasCost::from(4800));
+
+        let storage = &executor.world.account(address(CONTRACT)).unwrap().storage;
+        assert_eq!(storage.raw_get(U256::zero()), U256::zero());
+        assert_eq!(storage.original_value(U256::zero()), U256::zero());
+        assert_eq!(executor.world.account(address(SENDER)).unwrap().nonce, 3);
+        let total = executor
+            .receipts
+            .iter()
+            .map(|receipt| receipt.gas_used.as_u64());
+        assert_eq!(executor.gas_used().as_u64(), total.sum::<u64>());
+    }
+
+    #[test]
+    fn test_transient_storage_reset() {
+        // increments transient slot 0 and stores the result in slot 0
+        let mut executor = executor("60005c6001018060005d60005500");
+        for _ in 0..2 {
+            executor.execute(&call(0)).unwrap();
+            let storage = &executor.world.account(address(CONTRACT)).unwrap().storage;
+            assert_eq!(storage.raw_get(U256::zero()), U256::one());
+        }
+    }
+
+    #[test]
+    fn test_block_gas_limit() {
+        let mut executor = executor("");
+        executor.block.gas_limit = 120_000;
+        let tx = call(0);
+        executor.execute(&tx).unwrap();
+        let available = GasCost::from(120_000) - tx.intrinsic_gas(SpecId::Cancun);
+        assert_eq!(executor.gas_left(), available);
+        assert_eq!(
+            executor.execute(&tx),
+            Err(InvalidTransaction::BlockGasLimitExceeded {
+                gas_limit: GasCost::from(100_000),
+                available
+            })
+        );
+        assert_eq!(executor.receipts.len(), 1);
+    }
+
+    #[test]
+    fn test_next_base_fee() {
+        let mut executor = executor("");
+        executor.block.gas_limit = 100_000;
+        executor.block.base_fee = U256::from(800);
+        // an empty block lowers the base fee by an eighth
+        assert_eq!(executor.next_base_fee(), U256::from(700));
+        let tx = Transaction {
+            gas_price: U256::from(1000),
+            ..call(0)
+        };
+        executor.execute(&tx).unwrap();
+        // 21128 of the target of 50000 used
+        assert_eq!(executor.gas_used(), GasCost::from(21_128));
+        assert_eq!(executor.next_base_fee(), U256::from(743));
+    }
+}
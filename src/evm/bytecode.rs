@@ -1,4 +1,4 @@
-use crate::evm::OpcodeId;
+use crate::evm::{EvmError, OpcodeId};
 use rand::prelude::*;
 use std::fmt::Display;
 
@@ -17,13 +17,48 @@ pub struct BytecodeElement {
 }
 
 impl Bytecode {
-    pub fn get_opcode(&self, index: usize) -> Option<OpcodeId> {
-        let element = self.inner[index];
-        if element.is_code {
-            Some(OpcodeId::from(element.value))
-        } else {
-            None
+    /// Returns the opcode at `index`.
+    ///
+    /// The code is implicitly followed by `STOP`s, so an index past the end
+    /// returns `STOP`. Push data and invalid bytes return `InvalidOpcode` error.
+    pub fn get_opcode(&self, index: usize) -> Result<OpcodeId, EvmError> {
+        let Some(element) = self.inner.get(index) else {
+            return Ok(OpcodeId::STOP);
+        };
+        let opcode = OpcodeId::from(element.value);
+        if !element.is_code || opcode.is_invalid() {
+            return Err(EvmError::InvalidOpcode(element.value));
         }
+        Ok(opcode)
+    }
+
+    /// Returns the raw byte at `index`, push data included
+    pub fn get_byte(&self, index: usize) -> Option<u8> {
+        self.inner.get(index).map(|element| element.value)
+    }
+
+    /// Returns the raw bytes of the bytecode, including push data
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.iter().map(|element| element.value).collect()
+    }
+
+    /// Returns the jump destination bitmap of the bytecode.
+    ///
+    /// The n-th entry is `true` if the n-th byte is a `JUMPDEST` opcode,
+    /// `JUMPDEST` bytes inside push data are not valid destinations.
+    pub fn jump_destinations(&self) -> Vec<bool> {
+        self.inner
+            .iter()
+            .map(|element| element.is_code && element.value == OpcodeId::JUMPDEST.as_u8())
+            .collect()
+    }
+
+    /// Returns `true` if `index` is a valid jump destination.
+    pub fn is_jump_destination(&self, index: usize) -> bool {
+        self.inner
+            .get(index)
+            .map(|element| element.is_code && element.value == OpcodeId::JUMPDEST.as_u8())
+            .unwrap_or(false)
     }
 
     pub fn push(&mut self, instruction: Instruction) {
@@ -32,7 +67,8 @@ impl Bytecode {
             is_code: true,
         });
         if let Some(push_data) = instruction.push_data {
-            for byte in push_data {
+            let n_bytes = instruction.opcode.as_usize() - OpcodeId::PUSH0.as_usize();
+            for byte in push_data.into_iter().take(n_bytes) {
                 self.inner.push(BytecodeElement {
                     value: byte,
                     is_code: false,
@@ -42,6 +78,35 @@ impl Bytecode {
     }
 }
 
+impl From<&[u8]> for Bytecode {
+    /// Loads raw bytecode, marking push data bytes as non-code.
+    ///
+    /// A truncated push at the end of the code keeps only the bytes present.
+    fn from(bytes: &[u8]) -> Self {
+        let mut inner = Vec::with_capacity(bytes.len());
+        let mut push_data_left = 0;
+        for &value in bytes {
+            if push_data_left > 0 {
+                push_data_left -= 1;
+                inner.push(BytecodeElement {
+                    value,
+                    is_code: false,
+                });
+                continue;
+            }
+            let opcode = OpcodeId::from(value);
+            if opcode.is_push() {
+                push_data_left = opcode.as_usize() - OpcodeId::PUSH0.as_usize();
+            }
+            inner.push(BytecodeElement {
+                value,
+                is_code: true,
+            });
+        }
+        Bytecode { inner }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Instruction {
     pub opcode: OpcodeId,
@@ -81,3 +146,63 @@ impl Display for Instruction {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bytes() {
+        // PUSH2 0x5b00, INVALID, unassigned 0x0c, truncated PUSH2
+        let bytecode = Bytecode::from(&[0x61, 0x5b, 0x00, 0xfe, 0x0c, 0x61, 0x01][..]);
+        assert_eq!(
+            bytecode
+                .inner
+                .iter()
+                .map(|element| element.is_code)
+                .collect::<Vec<_>>(),
+            vec![true, false, false, true, true, true, false]
+        );
+        assert_eq!(bytecode.get_opcode(0), Ok(OpcodeId::PUSH2));
+        assert_eq!(bytecode.get_opcode(1), Err(EvmError::InvalidOpcode(0x5b)));
+        assert_eq!(bytecode.get_opcode(3), Err(EvmError::InvalidOpcode(0xfe)));
+        assert_eq!(bytecode.get_opcode(4), Err(EvmError::InvalidOpcode(0x0c)));
+        assert_eq!(bytecode.get_opcode(7), Ok(OpcodeId::STOP));
+        assert!(bytecode.jump_destinations().iter().all(|&dest| !dest));
+    }
+
+    #[test]
+    fn test_to_bytes() {
+        let bytes = [0x61, 0x5b, 0x00, 0xfe, 0x0c, 0x61, 0x01];
+        assert_eq!(Bytecode::from(&bytes[..]).to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_jump_destinations() {
+        let mut bytecode = Bytecode::default();
+        bytecode.push(Instruction {
+            opcode: OpcodeId::JUMPDEST,
+            push_data: None,
+        });
+        // push data that looks like a JUMPDEST
+        let mut push_data = [0u8; 32];
+        push_data[0] = OpcodeId::JUMPDEST.as_u8();
+        push_data[1] = OpcodeId::JUMPDEST.as_u8();
+        bytecode.push(Instruction {
+            opcode: OpcodeId::PUSH2,
+            push_data: Some(push_data),
+        });
+        bytecode.push(Instruction {
+            opcode: OpcodeId::JUMPDEST,
+            push_data: None,
+        });
+        assert_eq!(
+            bytecode.jump_destinations(),
+            vec![true, false, false, false, true]
+        );
+        assert!(bytecode.is_jump_destination(0));
+        assert!(!bytecode.is_jump_destination(2));
+        assert!(bytecode.is_jump_destination(4));
+        assert!(!bytecode.is_jump_destination(5));
+    }
+}
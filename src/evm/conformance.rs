@@ -0,0 +1,324 @@
+//! Table-driven conformance tests of the arithmetic and bitwise opcodes.
+//!
+//! The cases follow the edge cases of the `vmArithmeticTest` and
+//! `vmBitwiseLogicOperation` suites of ethereum/tests, with the expected
+//! values from the Yellow Paper definitions.
+
+use crate::evm::{Env, Evm, Instruction, OpcodeId};
+use primitive_types::U256;
+use std::str::FromStr;
+
+/// Case name, opcode, operands with the top of the stack first, expected result
+type Case = (
+    &'static str,
+    OpcodeId,
+    &'static [&'static str],
+    &'static str,
+);
+
+const CASES: &[Case] = &[
+    (
+        "addmod without overflow",
+        OpcodeId::ADDMOD,
+        &["0x1", "0x2", "0x2"],
+        "0x1",
+    ),
+    (
+        "addmod wrapping past 2^256",
+        OpcodeId::ADDMOD,
+        &[
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "0x2",
+            "0x2",
+        ],
+        "0x1",
+    ),
+    (
+        "addmod of the largest words",
+        OpcodeId::ADDMOD,
+        &[
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe",
+        ],
+        "0x2",
+    ),
+    (
+        "addmod with a modulus of 3",
+        OpcodeId::ADDMOD,
+        &[
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "0x1",
+            "0x3",
+        ],
+        "0x1",
+    ),
+    (
+        "addmod by zero",
+        OpcodeId::ADDMOD,
+        &["0x1", "0x2", "0x0"],
+        "0x0",
+    ),
+    (
+        "mulmod without overflow",
+        OpcodeId::MULMOD,
+        &["0x3", "0x5", "0x7"],
+        "0x1",
+    ),
+    (
+        "mulmod wrapping past 2^256",
+        OpcodeId::MULMOD,
+        &[
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "0xc",
+        ],
+        "0x9",
+    ),
+    (
+        "mulmod of the largest words",
+        OpcodeId::MULMOD,
+        &[
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe",
+        ],
+        "0x1",
+    ),
+    (
+        "mulmod of 2^255 by 2",
+        OpcodeId::MULMOD,
+        &[
+            "0x8000000000000000000000000000000000000000000000000000000000000000",
+            "0x2",
+            "0x3",
+        ],
+        "0x1",
+    ),
+    (
+        "mulmod by zero",
+        OpcodeId::MULMOD,
+        &[
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "0x0",
+        ],
+        "0x0",
+    ),
+    ("sdiv of positives", OpcodeId::SDIV, &["0x7", "0x2"], "0x3"),
+    (
+        "sdiv of a negative",
+        OpcodeId::SDIV,
+        &[
+            "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff9",
+            "0x2",
+        ],
+        "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd",
+    ),
+    (
+        "sdiv by a negative",
+        OpcodeId::SDIV,
+        &[
+            "0x7",
+            "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe",
+        ],
+        "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd",
+    ),
+    (
+        "sdiv of negatives",
+        OpcodeId::SDIV,
+        &[
+            "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff9",
+            "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe",
+        ],
+        "0x3",
+    ),
+    (
+        "sdiv of -2^255 by -1",
+        OpcodeId::SDIV,
+        &[
+            "0x8000000000000000000000000000000000000000000000000000000000000000",
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        ],
+        "0x8000000000000000000000000000000000000000000000000000000000000000",
+    ),
+    (
+        "sdiv of -2^255 by 1",
+        OpcodeId::SDIV,
+        &[
+            "0x8000000000000000000000000000000000000000000000000000000000000000",
+            "0x1",
+        ],
+        "0x8000000000000000000000000000000000000000000000000000000000000000",
+    ),
+    (
+        "sdiv by zero",
+        OpcodeId::SDIV,
+        &[
+            "0x8000000000000000000000000000000000000000000000000000000000000000",
+            "0x0",
+        ],
+        "0x0",
+    ),
+    (
+        "smod of a negative",
+        OpcodeId::SMOD,
+        &[
+            "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff8",
+            "0x3",
+        ],
+        "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe",
+    ),
+    (
+        "smod by a negative",
+        OpcodeId::SMOD,
+        &[
+            "0x8",
+            "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd",
+        ],
+        "0x2",
+    ),
+    (
+        "smod of negatives",
+        OpcodeId::SMOD,
+        &[
+            "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff8",
+            "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd",
+        ],
+        "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe",
+    ),
+    (
+        "smod of -2^255 by -1",
+        OpcodeId::SMOD,
+        &[
+            "0x8000000000000000000000000000000000000000000000000000000000000000",
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        ],
+        "0x0",
+    ),
+    (
+        "smod by zero",
+        OpcodeId::SMOD,
+        &[
+            "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff8",
+            "0x0",
+        ],
+        "0x0",
+    ),
+    (
+        "signextend of a negative byte",
+        OpcodeId::SIGNEXTEND,
+        &["0x0", "0xff"],
+        "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+    ),
+    (
+        "signextend of a positive byte",
+        OpcodeId::SIGNEXTEND,
+        &["0x0", "0x7f"],
+        "0x7f",
+    ),
+    (
+        "signextend clearing higher bytes",
+        OpcodeId::SIGNEXTEND,
+        &["0x0", "0x127f"],
+        "0x7f",
+    ),
+    (
+        "signextend setting higher bytes",
+        OpcodeId::SIGNEXTEND,
+        &["0x1", "0xff8000"],
+        "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff8000",
+    ),
+    (
+        "signextend of byte 30",
+        OpcodeId::SIGNEXTEND,
+        &[
+            "0x1e",
+            "0x80000000000000000000000000000000000000000000000000000000000000",
+        ],
+        "0xff80000000000000000000000000000000000000000000000000000000000000",
+    ),
+    (
+        "signextend of byte 31",
+        OpcodeId::SIGNEXTEND,
+        &["0x1f", "0x12"],
+        "0x12",
+    ),
+    (
+        "signextend of a byte past the word",
+        OpcodeId::SIGNEXTEND,
+        &["0x50", "0x126af4"],
+        "0x126af4",
+    ),
+    (
+        "signextend of a byte number past 2^64",
+        OpcodeId::SIGNEXTEND,
+        &["0x10000000000000000", "0xff"],
+        "0xff",
+    ),
+    (
+        "byte 31 is the least significant",
+        OpcodeId::BYTE,
+        &["0x1f", "0x1234"],
+        "0x34",
+    ),
+    ("byte 30", OpcodeId::BYTE, &["0x1e", "0x1234"], "0x12"),
+    (
+        "byte 0 is the most significant",
+        OpcodeId::BYTE,
+        &[
+            "0x0",
+            "0xab00000000000000000000000000000000000000000000000000000000000000",
+        ],
+        "0xab",
+    ),
+    (
+        "byte of a byte number past the word",
+        OpcodeId::BYTE,
+        &[
+            "0x20",
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        ],
+        "0x0",
+    ),
+    (
+        "byte of a huge byte number",
+        OpcodeId::BYTE,
+        &[
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        ],
+        "0x0",
+    ),
+];
+
+/// Runs `opcode` on `operands` and returns the top of the stack
+fn execute(opcode: OpcodeId, operands: &[U256]) -> U256 {
+    let mut evm = Evm::new(Env::default(), 1000);
+    for operand in operands.iter().rev() {
+        evm.stack.try_push(*operand).unwrap();
+    }
+    evm.push_instruction(Instruction {
+        opcode,
+        push_data: None,
+    })
+    .unwrap();
+    evm.step().unwrap();
+    assert_eq!(evm.stack.inner.len(), 1);
+    evm.stack.inner[0]
+}
+
+#[test]
+fn test_conformance() {
+    for &(name, opcode, operands, expected) in CASES {
+        let operands: Vec<_> = operands
+            .iter()
+            .map(|operand| U256::from_str(operand).unwrap())
+            .collect();
+        assert_eq!(
+            execute(opcode, &operands),
+            U256::from_str(expected).unwrap(),
+            "{name}"
+        );
+    }
+}
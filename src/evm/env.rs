@@ -0,0 +1,64 @@
+use crate::evm::AccessListItem;
+use primitive_types::{H160, U256};
+
+/// A 20-byte account address
+pub type Address = H160;
+
+/// The execution environment, read by the environmental opcodes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Env {
+    /// Chain id, read by `CHAINID`
+    pub chain_id: u64,
+    /// The block being executed
+    pub block: BlockEnv,
+    /// The transaction being executed
+    pub tx: TxEnv,
+}
+
+/// Block context of the execution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockEnv {
+    /// Beneficiary of the block, read by `COINBASE`
+    pub coinbase: Address,
+    /// Block timestamp, read by `TIMESTAMP`
+    pub timestamp: u64,
+    /// Block number, read by `NUMBER`
+    pub number: u64,
+    /// Randomness from the beacon chain, or block difficulty before the merge,
+    /// read by `DIFFICULTY`
+    pub prevrandao: U256,
+    /// Block gas limit, read by `GASLIMIT`
+    pub gas_limit: u64,
+    /// Base fee per gas, read by `BASEFEE`
+    pub base_fee: U256,
+}
+
+impl Default for BlockEnv {
+    fn default() -> Self {
+        BlockEnv {
+            coinbase: Address::zero(),
+            timestamp: 0,
+            number: 0,
+            prevrandao: U256::zero(),
+            gas_limit: 30_000_000,
+            base_fee: U256::zero(),
+        }
+    }
+}
+
+/// Transaction context of the execution
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxEnv {
+    /// Sender of the transaction, read by `ORIGIN`
+    pub origin: Address,
+    /// Effective gas price, read by `GASPRICE`
+    pub gas_price: U256,
+    /// Caller of the top level call
+    pub caller: Address,
+    /// Address of the top level call
+    pub address: Address,
+    /// Value sent with the top level call
+    pub value: U256,
+    /// Accounts and storage slots warm from the start, EIP-2930
+    pub access_list: Vec<AccessListItem>,
+}
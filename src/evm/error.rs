@@ -0,0 +1,259 @@
+use crate::evm::{GasCost, OpcodeId, MAX_MEMORY_SIZE};
+use primitive_types::U256;
+use std::fmt::Display;
+
+/// An exceptional halt, with the numbers that caused it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvmError {
+    /// Not enough gas left for the instruction
+    OutOfGas {
+        required: GasCost,
+        available: GasCost,
+    },
+    /// Fewer items on the stack than the instruction takes
+    StackUnderflow { required: usize, available: usize },
+    /// Pushing onto a full stack
+    StackOverflow,
+    /// Jumping to a location which is not a `JUMPDEST`
+    InvalidJump { destination: U256 },
+    /// Undefined byte, push data, or an opcode of a later fork
+    InvalidOpcode(u8),
+    /// State modification in a static context
+    WriteProtection,
+    /// `RETURNDATACOPY` reading past the end of the return data
+    ReturnDataOutOfBounds {
+        offset: U256,
+        size: U256,
+        available: usize,
+    },
+    /// Init code larger than `MAX_INITCODE_SIZE`
+    InitCodeSizeExceeded,
+    /// Deployed code larger than `MAX_CODE_SIZE`
+    CodeSizeExceeded,
+    /// Deployed code starting with 0xEF, from London
+    InvalidCodePrefix,
+    /// Memory growing past `MAX_MEMORY_SIZE`
+    MemoryLimitExceeded,
+    /// Precompile called with malformed input
+    PrecompileFailure,
+}
+
+impl EvmError {
+    /// Returns an `OutOfGas` error for an instruction costing `required`
+    pub fn out_of_gas(required: impl Into<GasCost>, available: impl Into<GasCost>) -> Self {
+        EvmError::OutOfGas {
+            required: required.into(),
+            available: available.into(),
+        }
+    }
+}
+
+impl Display for EvmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvmError::OutOfGas {
+                required,
+                available,
+            } => write!(
+                f,
+                "needed {} gas, had {}",
+                required.as_u64(),
+                available.as_u64()
+            ),
+            EvmError::StackUnderflow {
+                required,
+                available,
+            } => write!(f, "needed {required} stack items, had {available}"),
+            EvmError::StackOverflow => write!(f, "overflowed the stack"),
+            EvmError::InvalidJump { destination } => {
+                write!(f, "jumped to {destination:#x}, which is not a JUMPDEST")
+            }
+            EvmError::InvalidOpcode(byte) => write!(f, "{byte:#04x} is not a valid opcode"),
+            EvmError::WriteProtection => write!(f, "modified state in a static call"),
+            EvmError::ReturnDataOutOfBounds {
+                offset,
+                size,
+                available,
+            } => write!(
+                f,
+                "read {size} bytes at offset {offset} of {available} bytes of return data"
+            ),
+            EvmError::InitCodeSizeExceeded => write!(f, "exceeded the init code size limit"),
+            EvmError::CodeSizeExceeded => write!(f, "exceeded the code size limit"),
+            EvmError::InvalidCodePrefix => write!(f, "deployed code starting with 0xef"),
+            EvmError::MemoryLimitExceeded => {
+                write!(f, "exceeded the memory limit of {MAX_MEMORY_SIZE} bytes")
+            }
+            EvmError::PrecompileFailure => write!(f, "failed on malformed input"),
+        }
+    }
+}
+
+impl std::error::Error for EvmError {}
+
+/// An `EvmError` together with the instruction which raised it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionError {
+    /// Program counter of the instruction
+    pub pc: usize,
+    /// The instruction, `INVALID` for undefined bytes
+    pub opcode: OpcodeId,
+    /// What went wrong
+    pub error: EvmError,
+}
+
+impl Display for ExecutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.error {
+            // the byte alone names the culprit
+            EvmError::InvalidOpcode(_) => write!(f, "{} at pc {:#04x}", self.error, self.pc),
+            _ => write!(f, "{:?} {} at pc {:#04x}", self.opcode, self.error, self.pc),
+        }
+    }
+}
+
+impl std::error::Error for ExecutionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// A transaction which cannot be included, rejected before running any code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidTransaction {
+    /// The gas limit does not cover the intrinsic gas
+    IntrinsicGasTooLow {
+        intrinsic_gas: GasCost,
+        gas_limit: GasCost,
+    },
+    /// The sender cannot pay for the gas limit and the value
+    InsufficientBalance { required: U256, available: U256 },
+    /// The max fee per gas is less than the base fee of the block
+    FeeCapTooLow {
+        max_fee_per_gas: U256,
+        base_fee: U256,
+    },
+    /// The priority fee per gas is more than the max fee per gas
+    PriorityFeeTooHigh {
+        max_priority_fee_per_gas: U256,
+        max_fee_per_gas: U256,
+    },
+    /// Creation with init code larger than `MAX_INITCODE_SIZE`, from Shanghai
+    InitCodeSizeExceeded,
+    /// Creation at an address which already has code or a nonce
+    CreateCollision,
+    /// The gas limit at the max fee plus the value does not fit in 256 bits
+    FeeOverflow,
+    /// The nonce of the sender is `u64::MAX`, so it cannot be incremented, EIP-2681
+    NonceOverflow,
+    /// The gas limit is more than the gas left in the block
+    BlockGasLimitExceeded {
+        gas_limit: GasCost,
+        available: GasCost,
+    },
+}
+
+impl Display for InvalidTransaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidTransaction::IntrinsicGasTooLow {
+                intrinsic_gas,
+                gas_limit,
+            } => write!(
+                f,
+                "intrinsic gas of {} exceeds the gas limit of {}",
+                intrinsic_gas.as_u64(),
+                gas_limit.as_u64()
+            ),
+            InvalidTransaction::InsufficientBalance {
+                required,
+                available,
+            } => write!(f, "needed {required} wei, had {available}"),
+            InvalidTransaction::FeeCapTooLow {
+                max_fee_per_gas,
+                base_fee,
+            } => write!(
+                f,
+                "max fee per gas of {max_fee_per_gas} wei is less than the base fee of {base_fee}"
+            ),
+            InvalidTransaction::PriorityFeeTooHigh {
+                max_priority_fee_per_gas,
+                max_fee_per_gas,
+            } => write!(
+                f,
+                "priority fee per gas of {max_priority_fee_per_gas} wei exceeds \
+                 the max fee per gas of {max_fee_per_gas}"
+            ),
+            InvalidTransaction::InitCodeSizeExceeded => {
+                write!(f, "exceeded the init code size limit")
+            }
+            InvalidTransaction::CreateCollision => {
+                write!(f, "created a contract where one already exists")
+            }
+            InvalidTransaction::FeeOverflow => {
+                write!(f, "the gas limit at the max fee plus the value overflows")
+            }
+            InvalidTransaction::NonceOverflow => {
+                write!(f, "the nonce of the sender is at its maximum")
+            }
+            InvalidTransaction::BlockGasLimitExceeded {
+                gas_limit,
+                available,
+            } => write!(
+                f,
+                "gas limit of {} exceeds the {} gas left in the block",
+                gas_limit.as_u64(),
+                available.as_u64()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InvalidTransaction {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let error = ExecutionError {
+            pc: 0x0c,
+            opcode: OpcodeId::SWAP3,
+            error: EvmError::StackUnderflow {
+                required: 4,
+                available: 2,
+            },
+        };
+        assert_eq!(
+            error.to_string(),
+            "SWAP3 needed 4 stack items, had 2 at pc 0x0c"
+        );
+        let error = ExecutionError {
+            pc: 0x102,
+            opcode: OpcodeId::SSTORE,
+            error: EvmError::out_of_gas(22100, 5000),
+        };
+        assert_eq!(
+            error.to_string(),
+            "SSTORE needed 22100 gas, had 5000 at pc 0x102"
+        );
+        let error = ExecutionError {
+            pc: 2,
+            opcode: OpcodeId::INVALID(0xfe),
+            error: EvmError::InvalidOpcode(0xfe),
+        };
+        assert_eq!(error.to_string(), "0xfe is not a valid opcode at pc 0x02");
+        let error = ExecutionError {
+            pc: 7,
+            opcode: OpcodeId::JUMP,
+            error: EvmError::InvalidJump {
+                destination: U256::from(0x2a),
+            },
+        };
+        assert_eq!(
+            error.to_string(),
+            "JUMP jumped to 0x2a, which is not a JUMPDEST at pc 0x07"
+        );
+    }
+}
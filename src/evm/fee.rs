@@ -0,0 +1,234 @@
+use crate::evm::{GasCost, InvalidTransaction};
+use primitive_types::U256;
+use std::fmt::Display;
+
+/// Wei in a gwei
+pub const GWEI: u64 = 1_000_000_000;
+/// Wei in an ether
+pub const ETHER: u64 = 1_000_000_000_000_000_000;
+/// The base fee changes by at most one eighth per block, EIP-1559
+pub const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
+/// The gas target of a block is its gas limit divided by this, EIP-1559
+pub const ELASTICITY_MULTIPLIER: u64 = 2;
+
+/// Fee caps of an EIP-1559 transaction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DynamicFee {
+    /// Most the sender pays per gas, base fee included
+    pub max_fee_per_gas: U256,
+    /// Most the sender tips the coinbase per gas
+    pub max_priority_fee_per_gas: U256,
+}
+
+impl DynamicFee {
+    /// The caps a legacy transaction paying `gas_price` amounts to
+    pub fn legacy(gas_price: U256) -> Self {
+        DynamicFee {
+            max_fee_per_gas: gas_price,
+            max_priority_fee_per_gas: gas_price,
+        }
+    }
+
+    /// Returns the price paid per gas in a block with `base_fee`:
+    /// the base fee plus as much of the priority fee as the max fee leaves room for
+    ///
+    /// # Returns
+    ///
+    /// Returns the effective gas price.
+    /// When the priority fee is more than the max fee, returns `PriorityFeeTooHigh` error.
+    /// When the max fee is less than `base_fee`, returns `FeeCapTooLow` error.
+    pub fn effective_gas_price(&self, base_fee: U256) -> Result<U256, InvalidTransaction> {
+        if self.max_priority_fee_per_gas > self.max_fee_per_gas {
+            return Err(InvalidTransaction::PriorityFeeTooHigh {
+                max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+                max_fee_per_gas: self.max_fee_per_gas,
+            });
+        }
+        if self.max_fee_per_gas < base_fee {
+            return Err(InvalidTransaction::FeeCapTooLow {
+                max_fee_per_gas: self.max_fee_per_gas,
+                base_fee,
+            });
+        }
+        Ok(self
+            .max_fee_per_gas
+            .min(base_fee.saturating_add(self.max_priority_fee_per_gas)))
+    }
+}
+
+/// What the gas of a transaction costs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fee {
+    /// Gas paid for
+    pub gas_used: GasCost,
+    /// Effective price in wei of every unit of gas
+    pub gas_price: U256,
+    /// Part of the price which is burnt, zero before London
+    pub base_fee: U256,
+}
+
+impl Fee {
+    /// Returns the fee in wei
+    pub fn total(&self) -> U256 {
+        U256::from(self.gas_used.as_u64()).saturating_mul(self.gas_price)
+    }
+
+    /// Returns the part of the fee which is burnt, in wei
+    pub fn burnt(&self) -> U256 {
+        U256::from(self.gas_used.as_u64()).saturating_mul(self.base_fee)
+    }
+
+    /// Returns the part of the fee the coinbase earns, in wei
+    pub fn tip(&self) -> U256 {
+        self.total() - self.burnt()
+    }
+
+    /// Returns the fee in gwei, e.g. `"1234.5"`
+    pub fn gwei(&self) -> String {
+        format_units(self.total(), GWEI)
+    }
+
+    /// Returns the fee in ether, e.g. `"0.0012"`
+    pub fn ether(&self) -> String {
+        format_units(self.total(), ETHER)
+    }
+}
+
+impl Display for Fee {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ETH", self.ether())
+    }
+}
+
+/// Writes `wei` in a unit worth `unit` wei, without trailing zeros
+pub fn format_units(wei: U256, unit: u64) -> String {
+    let (whole, fraction) = wei.div_mod(U256::from(unit));
+    if fraction.is_zero() {
+        return whole.to_string();
+    }
+    let digits = unit.ilog10() as usize;
+    let fraction = format!("{:0>digits$}", fraction.to_string());
+    format!("{whole}.{}", fraction.trim_end_matches('0'))
+}
+
+/// Returns the base fee of the block after one with `base_fee` which used `gas_used`
+/// of its `gas_limit`, EIP-1559.
+///
+/// The base fee rises when the block used more than its target of half the gas limit,
+/// and falls when it used less, by at most one eighth.
+pub fn next_base_fee(base_fee: U256, gas_used: GasCost, gas_limit: u64) -> U256 {
+    let target = gas_limit / ELASTICITY_MULTIPLIER;
+    let gas_used = gas_used.as_u64();
+    if target == 0 || gas_used == target {
+        return base_fee;
+    }
+    let change = |delta: u64| {
+        base_fee.saturating_mul(U256::from(delta))
+            / U256::from(target)
+            / U256::from(BASE_FEE_MAX_CHANGE_DENOMINATOR)
+    };
+    if gas_used > target {
+        // rises by at least one wei
+        base_fee.saturating_add(change(gas_used - target).max(U256::one()))
+    } else {
+        base_fee - change(target - gas_used)
+    }
+}
+
+/// Returns the base fees of consecutive blocks with `gas_limit`, starting at `base_fee`,
+/// when they use `gas_used` one after another
+pub fn base_fee_schedule(
+    base_fee: U256,
+    gas_limit: u64,
+    gas_used: impl IntoIterator<Item = GasCost>,
+) -> Vec<U256> {
+    let mut base_fees = vec![base_fee];
+    for gas_used in gas_used {
+        let parent = *base_fees.last().unwrap();
+        base_fees.push(next_base_fee(parent, gas_used, gas_limit));
+    }
+    base_fees
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gwei(n: u64) -> U256 {
+        U256::from(n) * GWEI
+    }
+
+    #[test]
+    fn test_effective_gas_price() {
+        let fee = DynamicFee {
+            max_fee_per_gas: gwei(30),
+            max_priority_fee_per_gas: gwei(2),
+        };
+        assert_eq!(fee.effective_gas_price(gwei(10)), Ok(gwei(12)));
+        // the max fee caps the tip
+        assert_eq!(fee.effective_gas_price(gwei(29)), Ok(gwei(30)));
+        assert_eq!(
+            fee.effective_gas_price(gwei(31)),
+            Err(InvalidTransaction::FeeCapTooLow {
+                max_fee_per_gas: gwei(30),
+                base_fee: gwei(31),
+            })
+        );
+        let fee = DynamicFee {
+            max_priority_fee_per_gas: gwei(40),
+            ..fee
+        };
+        assert!(matches!(
+            fee.effective_gas_price(gwei(10)),
+            Err(InvalidTransaction::PriorityFeeTooHigh { .. })
+        ));
+        // a legacy transaction pays its gas price
+        let legacy = DynamicFee::legacy(gwei(20));
+        assert_eq!(legacy.effective_gas_price(gwei(10)), Ok(gwei(20)));
+    }
+
+    #[test]
+    fn test_fee() {
+        let fee = Fee {
+            gas_used: GasCost::from(60_000),
+            gas_price: gwei(20),
+            base_fee: gwei(15),
+        };
+        assert_eq!(fee.total(), U256::from(1_200_000) * GWEI);
+        assert_eq!(fee.burnt(), U256::from(900_000) * GWEI);
+        assert_eq!(fee.tip(), U256::from(300_000) * GWEI);
+        assert_eq!(fee.gwei(), "1200000");
+        assert_eq!(fee.ether(), "0.0012");
+        assert_eq!(fee.to_string(), "0.0012 ETH");
+
+        assert_eq!(format_units(U256::from(1_500_000_001), GWEI), "1.500000001");
+        assert_eq!(format_units(U256::from(12), GWEI), "0.000000012");
+        assert_eq!(format_units(U256::zero(), ETHER), "0");
+        assert_eq!(format_units(U256::from(3) * ETHER, ETHER), "3");
+    }
+
+    #[test]
+    fn test_next_base_fee() {
+        let gas_limit = 30_000_000;
+        let target = GasCost::from(15_000_000);
+        assert_eq!(next_base_fee(gwei(8), target, gas_limit), gwei(8));
+        // a full block raises the base fee by an eighth, an empty one lowers it by an eighth
+        assert_eq!(
+            next_base_fee(gwei(8), GasCost::from(gas_limit), gas_limit),
+            gwei(9)
+        );
+        assert_eq!(next_base_fee(gwei(8), GasCost::ZERO, gas_limit), gwei(7));
+        // rises by at least one wei
+        assert_eq!(
+            next_base_fee(U256::from(7), target + 1, gas_limit),
+            U256::from(8)
+        );
+
+        let schedule = base_fee_schedule(
+            gwei(64),
+            gas_limit,
+            [GasCost::from(gas_limit), GasCost::from(gas_limit), target],
+        );
+        assert_eq!(schedule, [gwei(64), gwei(72), gwei(81), gwei(81)]);
+    }
+}
@@ -0,0 +1,1290 @@
+use crate::evm::utils::{address_to_word, create2_address, create_address, word_to_address};
+use crate::evm::{
+    precompile, Address, Bytecode, Evm, EvmError, ExecutionError, Gas, GasCategory, GasCost,
+    HaltReason, Memory, OpcodeId, PrecompileOutput, SpecId, Stack,
+};
+use primitive_types::U256;
+use std::mem;
+
+/// Maximum depth of nested calls
+pub const MAX_CALL_DEPTH: usize = 1024;
+/// Maximum size in bytes of deployed code, EIP-170
+pub const MAX_CODE_SIZE: usize = 24576;
+/// Maximum size in bytes of init code, EIP-3860
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+/// Message context of the running code
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallContext {
+    /// Address whose storage and balance the code runs against, read by `ADDRESS`
+    pub address: Address,
+    /// Caller of the running code, read by `CALLER`
+    pub caller: Address,
+    /// Value sent with the call, read by `CALLVALUE`
+    pub value: U256,
+    /// State modifications are forbidden, set by `STATICCALL`
+    pub is_static: bool,
+}
+
+/// State to restore when a frame reverts, the world state and the access list
+/// are restored from their journals
+#[derive(Debug, Clone)]
+struct Snapshot {
+    logs_len: usize,
+}
+
+/// What the caller does with the result of its callee
+#[derive(Debug, Clone)]
+enum FrameKind {
+    /// Copies the return data to `ret_size` bytes of memory at `ret_offset`
+    Call { ret_offset: usize, ret_size: usize },
+    /// Deploys the returned code at `address`
+    Create { address: Address },
+}
+
+/// A suspended caller frame, waiting for its callee to halt
+#[derive(Debug, Clone)]
+pub struct CallFrame {
+    pub program_counter: usize,
+    pub bytecode: Bytecode,
+    pub gas: Gas,
+    pub memory: Memory,
+    pub stack: Stack,
+    pub calldata: Vec<u8>,
+    pub context: CallContext,
+    kind: FrameKind,
+    /// State before the call
+    snapshot: Snapshot,
+}
+
+impl Evm {
+    /// Returns the depth of the running frame, zero for the top level frame
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Implementation of the CALL, CALLCODE, DELEGATECALL and STATICCALL opcodes
+    ///
+    /// # Stack Inputs
+    /// - `gas`: amount of gas to send to the callee
+    /// - `address`: the account whose code runs
+    /// - `value`: value in wei to send, only for `CALL` and `CALLCODE`
+    /// - `args_offset`, `args_size`: memory range of the calldata
+    /// - `ret_offset`, `ret_size`: memory range where the return data is copied
+    ///
+    /// # Stack Outputs
+    /// - `success`: pushed when the callee halts, 0 if it reverted or failed
+    ///
+    /// The callee gets all but one 64th of the gas left (EIP-150), capped at `gas`.
+    /// Before Tangerine, it gets `gas`, and asking for more than is left runs out of gas.
+    /// From Berlin, the first access of `address` costs `COLD_ACCOUNT_ACCESS` (EIP-2929).
+    /// A `CALL` creating an account costs `NEW_ACCOUNT`: from Spurious Dragon when it sends value
+    /// to an empty account (EIP-161), before whenever the account does not exist.
+    /// Precompiles run right away, without entering a new frame.
+    pub(crate) fn call(&mut self, opcode: OpcodeId) -> Result<(), EvmError> {
+        let gas = self.stack.try_pop()?;
+        let code_address = word_to_address(self.stack.try_pop()?);
+        let value = match opcode {
+            OpcodeId::CALL | OpcodeId::CALLCODE => self.stack.try_pop()?,
+            _ => U256::zero(),
+        };
+        let args_offset = self.stack.try_pop()?;
+        let args_size = self.stack.try_pop()?;
+        let ret_offset = self.stack.try_pop()?;
+        let ret_size = self.stack.try_pop()?;
+
+        if opcode == OpcodeId::CALL && self.context.is_static && !value.is_zero() {
+            return Err(EvmError::WriteProtection);
+        }
+
+        let base_cost = opcode.constant_gas_cost_in(self.spec);
+        let is_cold = self.spec.is_enabled_in(SpecId::Berlin)
+            && !self.access_list.is_warm_address(code_address);
+        let cold_cost = if is_cold {
+            GasCost::COLD_ACCOUNT_ACCESS - GasCost::WARM_ACCESS
+        } else {
+            GasCost::ZERO
+        };
+        let mut value_cost = GasCost::ZERO;
+        if !value.is_zero() {
+            value_cost += GasCost::CALL_WITH_VALUE;
+        }
+        let new_account = if self.spec.is_enabled_in(SpecId::SpuriousDragon) {
+            // only sending value to an empty account creates it, EIP-161
+            !value.is_zero() && self.world.is_empty(code_address)
+        } else {
+            self.world.account(code_address).is_none()
+        };
+        if opcode == OpcodeId::CALL && new_account {
+            value_cost += GasCost::NEW_ACCOUNT;
+        }
+        let args_size = Memory::checked_size(args_size)?;
+        let ret_size = Memory::checked_size(ret_size)?;
+        let charges = [
+            (GasCategory::Base, base_cost),
+            (GasCategory::ColdAccess, cold_cost),
+            (GasCategory::Dynamic, value_cost),
+        ];
+        let args_offset =
+            self.memory
+                .try_expand_range(args_offset, args_size, &charges, &mut self.gas)?;
+        let ret_offset = self
+            .memory
+            .try_expand_range(ret_offset, ret_size, &[], &mut self.gas)?;
+        self.access_list.add_warm_address(code_address);
+        if !value.is_zero() {
+            // the stipend is part of the value cost, the callee puts down what it does with it
+            self.gas
+                .pass_on(GasCategory::Dynamic, GasCost::CALL_STIPEND);
+        }
+
+        let left = self.gas.left().as_u64();
+        let cap = if self.spec.is_enabled_in(SpecId::Tangerine) {
+            left - left / 64
+        } else {
+            // asking for more gas than is left runs out of gas
+            if gas > U256::from(left) {
+                return Err(EvmError::out_of_gas(
+                    gas.min(u64::MAX.into()).as_u64(),
+                    left,
+                ));
+            }
+            left
+        };
+        let forwarded = if gas > U256::from(cap) {
+            cap
+        } else {
+            gas.as_u64()
+        };
+        self.gas.forward(forwarded.into())?;
+        let mut callee_gas = GasCost::from(forwarded);
+        if !value.is_zero() {
+            callee_gas += GasCost::CALL_STIPEND;
+        }
+
+        let context = match opcode {
+            OpcodeId::CALL => CallContext {
+                address: code_address,
+                caller: self.context.address,
+                value,
+                is_static: self.context.is_static,
+            },
+            OpcodeId::CALLCODE => CallContext {
+                address: self.context.address,
+                caller: self.context.address,
+                value,
+                is_static: self.context.is_static,
+            },
+            OpcodeId::DELEGATECALL => self.context.clone(),
+            OpcodeId::STATICCALL => CallContext {
+                address: code_address,
+                caller: self.context.address,
+                value: U256::zero(),
+                is_static: true,
+            },
+            _ => unreachable!(),
+        };
+
+        self.program_counter += 1;
+        if self.depth() >= MAX_CALL_DEPTH || self.world.balance(self.context.address) < value {
+            // the call fails without running the callee, the gas and the stipend are given back
+            self.gas.reclaim(callee_gas);
+            self.return_data_buffer.clear();
+            self.stack.try_push(U256::zero())?;
+            return Ok(());
+        }
+
+        if let Some(precompile) = precompile(code_address, self.spec) {
+            let input = self.memory.raw_slice(args_offset, args_size);
+            let success = match precompile.run(input, callee_gas) {
+                Ok(PrecompileOutput { gas_used, output }) => {
+                    if opcode == OpcodeId::CALL {
+                        self.world
+                            .transfer(self.context.address, code_address, value);
+                    }
+                    self.gas.reclaim(callee_gas - gas_used);
+                    self.gas.record_charge(GasCategory::Dynamic, gas_used);
+                    self.return_data_buffer = output;
+                    self.copy_return_data(ret_offset, ret_size);
+                    true
+                }
+                // a failed precompile consumes all the gas it was given
+                Err(_) => {
+                    self.gas.record_charge(GasCategory::Exception, callee_gas);
+                    self.return_data_buffer.clear();
+                    false
+                }
+            };
+            self.stack.try_push(U256::from(success as u8))?;
+            return Ok(());
+        }
+
+        let snapshot = self.snapshot();
+        if opcode == OpcodeId::CALL {
+            self.world
+                .transfer(self.context.address, code_address, value);
+        }
+        let calldata = self.memory.raw_slice(args_offset, args_size).to_vec();
+        self.enter_frame(
+            self.world.code(code_address),
+            callee_gas,
+            calldata,
+            context,
+            FrameKind::Call {
+                ret_offset,
+                ret_size,
+            },
+            snapshot,
+        );
+        Ok(())
+    }
+
+    /// Implementation of the CREATE and CREATE2 opcodes
+    ///
+    /// # Stack Inputs
+    /// - `value`: value in wei to send to the new account
+    /// - `offset`, `size`: memory range of the init code
+    /// - `salt`: only for `CREATE2`, used to derive the new address
+    ///
+    /// # Stack Outputs
+    /// - `address`: pushed when the init code halts, 0 if the creation failed
+    ///
+    /// The init code gets all but one 64th of the gas left (EIP-150), all of it before Tangerine,
+    /// and the code it returns is deployed at the new address.
+    pub(crate) fn create(&mut self, opcode: OpcodeId) -> Result<(), EvmError> {
+        let value = self.stack.try_pop()?;
+        let offset = self.stack.try_pop()?;
+        let size = self.stack.try_pop()?;
+        let salt = match opcode {
+            OpcodeId::CREATE2 => self.stack.try_pop()?,
+            _ => U256::zero(),
+        };
+
+        if self.context.is_static {
+            return Err(EvmError::WriteProtection);
+        }
+        let size = Memory::checked_size(size)?;
+        let base_cost = opcode.constant_gas_cost_in(self.spec);
+        let mut word_cost = GasCost::ZERO;
+        if self.spec.is_enabled_in(SpecId::Shanghai) {
+            if size > MAX_INITCODE_SIZE {
+                return Err(EvmError::InitCodeSizeExceeded);
+            }
+            word_cost += GasCost::INITCODE_WORD_COST.per_word(size);
+        }
+        if opcode == OpcodeId::CREATE2 {
+            // hashing the init code to derive the address
+            word_cost += GasCost::COPY_SHA3.per_word(size);
+        }
+        let charges = [
+            (GasCategory::Base, base_cost),
+            (GasCategory::Copy, word_cost),
+        ];
+        let offset = self
+            .memory
+            .try_expand_range(offset, size, &charges, &mut self.gas)?;
+        let init_code = self.memory.raw_slice(offset, size).to_vec();
+
+        let left = self.gas.left().as_u64();
+        let forwarded = if self.spec.is_enabled_in(SpecId::Tangerine) {
+            left - left / 64
+        } else {
+            left
+        };
+        self.gas.forward(forwarded.into())?;
+
+        self.program_counter += 1;
+        self.return_data_buffer.clear();
+        let sender = self.context.address;
+        let nonce = self
+            .world
+            .account(sender)
+            .map_or(0, |account| account.nonce);
+        if self.depth() >= MAX_CALL_DEPTH || self.world.balance(sender) < value || nonce == u64::MAX
+        {
+            // the creation fails without running the init code, the gas is given back
+            self.gas.reclaim(forwarded.into());
+            self.stack.try_push(U256::zero())?;
+            return Ok(());
+        }
+        self.world.set_nonce(sender, nonce + 1);
+
+        let address = match opcode {
+            OpcodeId::CREATE => create_address(sender, nonce),
+            OpcodeId::CREATE2 => create2_address(sender, salt, &init_code),
+            _ => unreachable!(),
+        };
+        // the new address is warm even if the creation fails, EIP-2929
+        self.access_list.add_warm_address(address);
+        let collision = self
+            .world
+            .account(address)
+            .is_some_and(|account| account.nonce != 0 || !account.code.inner.is_empty());
+        if collision {
+            // the forwarded gas is consumed
+            self.gas
+                .record_charge(GasCategory::Exception, forwarded.into());
+            self.stack.try_push(U256::zero())?;
+            return Ok(());
+        }
+
+        let snapshot = self.snapshot();
+        self.init_contract_account(address);
+        self.world.transfer(sender, address, value);
+        let context = CallContext {
+            address,
+            caller: sender,
+            value,
+            is_static: false,
+        };
+        self.enter_frame(
+            Bytecode::from(&init_code[..]),
+            forwarded.into(),
+            Vec::new(),
+            context,
+            FrameKind::Create { address },
+            snapshot,
+        );
+        Ok(())
+    }
+
+    /// Sets up the account of a contract being created at `address`,
+    /// which starts with nonce 1 from Spurious Dragon (EIP-161)
+    pub(crate) fn init_contract_account(&mut self, address: Address) {
+        if self.spec.is_enabled_in(SpecId::SpuriousDragon) {
+            self.world.set_nonce(address, 1);
+        }
+    }
+
+    /// Captures the state to restore if the next frame fails
+    fn snapshot(&mut self) -> Snapshot {
+        self.world.checkpoint();
+        self.access_list.checkpoint();
+        Snapshot {
+            logs_len: self.logs.len(),
+        }
+    }
+
+    /// Suspends the running frame and starts running `bytecode` in a new one
+    fn enter_frame(
+        &mut self,
+        bytecode: Bytecode,
+        gas: GasCost,
+        calldata: Vec<u8>,
+        context: CallContext,
+        kind: FrameKind,
+        snapshot: Snapshot,
+    ) {
+        let caller = CallFrame {
+            program_counter: self.program_counter,
+            bytecode: mem::replace(&mut self.bytecode, bytecode),
+            gas: mem::replace(&mut self.gas, Gas::new(gas)),
+            memory: mem::take(&mut self.memory),
+            stack: mem::take(&mut self.stack),
+            calldata: mem::replace(&mut self.calldata, calldata),
+            context: mem::replace(&mut self.context, context),
+            kind,
+            snapshot,
+        };
+        self.frames.push(caller);
+        self.program_counter = 0;
+        self.return_data.clear();
+        self.return_data_buffer.clear();
+    }
+
+    /// Deploys `output` of init code which halted with `halt_reason` at `address`
+    ///
+    /// # Returns
+    ///
+    /// Returns `halt_reason`, or an exceptional halt consuming all the gas left
+    /// if the code could not be deposited.
+    pub(crate) fn finish_create(
+        &mut self,
+        address: Address,
+        halt_reason: HaltReason,
+        output: &[u8],
+    ) -> HaltReason {
+        let opcode = match halt_reason {
+            HaltReason::Stop => OpcodeId::STOP,
+            HaltReason::Return => OpcodeId::RETURN,
+            _ => return halt_reason,
+        };
+        match self.deposit_code(address, output) {
+            Ok(()) => halt_reason,
+            Err(error) => {
+                self.gas.consume_all();
+                HaltReason::Exception(ExecutionError {
+                    pc: self.program_counter,
+                    opcode,
+                    error,
+                })
+            }
+        }
+    }
+
+    /// Deploys `code` returned by init code at `address`, charging the code deposit cost
+    fn deposit_code(&mut self, address: Address, code: &[u8]) -> Result<(), EvmError> {
+        if self.spec.is_enabled_in(SpecId::SpuriousDragon) && code.len() > MAX_CODE_SIZE {
+            return Err(EvmError::CodeSizeExceeded);
+        }
+        // reserved for the EVM object format, EIP-3541
+        if self.spec.is_enabled_in(SpecId::London) && code.first() == Some(&0xef) {
+            return Err(EvmError::InvalidCodePrefix);
+        }
+        let cost = GasCost::CODE_DEPOSIT_BYTE_COST.as_u64() * code.len() as u64;
+        self.gas.charge([(GasCategory::Dynamic, cost.into())])?;
+        self.world.set_code(address, Bytecode::from(code));
+        Ok(())
+    }
+
+    /// Returns to the caller frame once the running frame halted.
+    ///
+    /// Copies at most `ret_size` bytes of the return data buffer to memory at `ret_offset`
+    fn copy_return_data(&mut self, ret_offset: usize, ret_size: usize) {
+        let size = ret_size.min(self.return_data_buffer.len());
+        self.memory
+            .raw_slice_mut(ret_offset, size)
+            .copy_from_slice(&self.return_data_buffer[..size]);
+    }
+
+    /// Deploys the returned code of init code,
+    /// reverts the state changes of the callee unless it halted with `STOP` or `RETURN`,
+    /// gives back the gas it did not use and keeps its refunds if it succeeded,
+    /// copies the return data and pushes the result.
+    pub(crate) fn exit_frame(&mut self, mut halt_reason: HaltReason) {
+        let caller = self
+            .frames
+            .pop()
+            .expect("exit_frame called in the top level frame");
+        let output = mem::take(&mut self.return_data);
+        if let FrameKind::Create { address } = caller.kind {
+            halt_reason = self.finish_create(address, halt_reason, &output);
+        }
+        let gas_left = self.gas.left();
+        let success = matches!(halt_reason, HaltReason::Stop | HaltReason::Return);
+        let mut charges = self.gas.take_charges();
+        if !success {
+            charges = charges.without_refunds();
+        }
+
+        self.program_counter = caller.program_counter;
+        self.bytecode = caller.bytecode;
+        self.gas = caller.gas;
+        self.memory = caller.memory;
+        self.stack = caller.stack;
+        self.calldata = caller.calldata;
+        self.context = caller.context;
+
+        if success {
+            self.world.discard_checkpoint();
+            self.access_list.discard_checkpoint();
+        } else {
+            self.world.revert_checkpoint();
+            self.access_list.revert_checkpoint();
+            self.logs.truncate(caller.snapshot.logs_len);
+        }
+        self.gas.reclaim(gas_left);
+        self.gas.absorb(charges);
+        self.return_data_buffer = match (&caller.kind, halt_reason) {
+            (_, HaltReason::Exception(_)) => Vec::new(),
+            // the output of successful init code is the deployed code
+            (FrameKind::Create { .. }, _) if success => Vec::new(),
+            _ => output,
+        };
+        let result = match caller.kind {
+            FrameKind::Call {
+                ret_offset,
+                ret_size,
+            } => {
+                self.copy_return_data(ret_offset, ret_size);
+                U256::from(success as u8)
+            }
+            FrameKind::Create { address } if success => address_to_word(address),
+            FrameKind::Create { .. } => U256::zero(),
+        };
+        // impossible to fail, the call popped more than one item
+        self.stack.try_push(result).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::utils::{address, address_to_word};
+    use crate::evm::{AccessListItem, Account, Env, HaltReason, Log};
+
+    const CALLER: u64 = 0xaa;
+    const CALLEE: u64 = 0xbb;
+
+    /// Assembles `PUSH1 value` for every `Some(value)`, and the opcode for every `None`
+    fn code(items: &[(OpcodeId, Option<u8>)]) -> Vec<u8> {
+        let mut code = Vec::new();
+        for &(opcode, value) in items {
+            code.push(opcode.as_u8());
+            code.extend(value);
+        }
+        code
+    }
+
+    /// Code calling `CALLEE` with all the gas left, `value` for `CALL` and `CALLCODE`,
+    /// and copying `ret_size` bytes of return data to memory offset 0
+    fn call_code(opcode: OpcodeId, value: u8, ret_size: u8) -> Vec<u8> {
+        let mut items = vec![
+            (OpcodeId::PUSH1, Some(ret_size)),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::PUSH1, Some(0)),
+        ];
+        if matches!(opcode, OpcodeId::CALL | OpcodeId::CALLCODE) {
+            items.push((OpcodeId::PUSH1, Some(value)));
+        }
+        items.push((OpcodeId::PUSH1, Some(CALLEE as u8)));
+        items.push((OpcodeId::GAS, None));
+        items.push((opcode, None));
+        code(&items)
+    }
+
+    /// Runs `caller_code` at `CALLER` with `callee_code` deployed at `CALLEE`
+    fn run_call(caller_code: &[u8], callee_code: &[u8]) -> Evm {
+        run_call_in(SpecId::default(), caller_code, callee_code)
+    }
+
+    /// Same as `run_call`, under the rules of `spec`
+    fn run_call_in(spec: SpecId, caller_code: &[u8], callee_code: &[u8]) -> Evm {
+        let mut env = Env::default();
+        env.tx.caller = Address::repeat_byte(0x01);
+        env.tx.address = address(CALLER);
+        let mut evm = Evm::new(env, 1_000_000).with_spec(spec);
+        evm.bytecode = Bytecode::from(caller_code);
+        evm.world.account_mut(evm.context.address).code = Bytecode::from(caller_code);
+        evm.world.insert_account(
+            address(CALLEE),
+            Account {
+                code: Bytecode::from(callee_code),
+                ..Account::default()
+            },
+        );
+        evm.run(1000);
+        evm
+    }
+
+    /// Returns slot 0 of the storage of `address`
+    fn slot_0(evm: &mut Evm, n: u64) -> U256 {
+        evm.world
+            .account_mut(address(n))
+            .storage
+            .raw_get(U256::zero())
+    }
+
+    #[test]
+    fn test_call_returns_data() {
+        let callee = code(&[
+            (OpcodeId::PUSH1, Some(0x2a)),
+            (OpcodeId::PUSH1, Some(31)),
+            (OpcodeId::MSTORE8, None),
+            (OpcodeId::PUSH1, Some(32)),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::RETURN, None),
+        ]);
+        let mut caller = call_code(OpcodeId::CALL, 0, 32);
+        caller.push(OpcodeId::RETURNDATASIZE.as_u8());
+        let evm = run_call(&caller, &callee);
+        assert_eq!(evm.halt_reason, Some(HaltReason::Stop));
+        assert!(evm.frames.is_empty());
+        assert_eq!(evm.stack.inner, vec![U256::one(), U256::from(32)]);
+        assert_eq!(evm.memory.raw_get(0), U256::from(0x2a));
+        assert_eq!(evm.return_data_buffer.len(), 32);
+        // the caller's own return data is untouched
+        assert!(evm.return_data.is_empty());
+    }
+
+    #[test]
+    fn test_call_reverts_state() {
+        for (halt, success) in [(OpcodeId::STOP, 1), (OpcodeId::REVERT, 0)] {
+            let callee = code(&[
+                (OpcodeId::PUSH1, Some(1)),
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::SSTORE, None),
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::LOG0, None),
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::PUSH1, Some(0)),
+                (halt, None),
+            ]);
+            let mut evm = run_call(&call_code(OpcodeId::CALL, 0, 0), &callee);
+            assert_eq!(evm.halt_reason, Some(HaltReason::Stop));
+            assert_eq!(evm.stack.inner, vec![U256::from(success)]);
+            assert_eq!(slot_0(&mut evm, CALLEE), U256::from(success));
+            let logs = if success == 1 {
+                vec![Log {
+                    address: address(CALLEE),
+                    ..Log::default()
+                }]
+            } else {
+                vec![]
+            };
+            assert_eq!(evm.logs, logs);
+        }
+    }
+
+    #[test]
+    fn test_call_refunds() {
+        for (halt, refund) in [(OpcodeId::STOP, 19900), (OpcodeId::REVERT, 0)] {
+            // sets slot 0 and clears it again
+            let callee = code(&[
+                (OpcodeId::PUSH1, Some(1)),
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::SSTORE, None),
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::SSTORE, None),
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::PUSH1, Some(0)),
+                (halt, None),
+            ]);
+            let evm = run_call(&call_code(OpcodeId::CALL, 0, 0), &callee);
+            assert_eq!(evm.halt_reason, Some(HaltReason::Stop));
+            assert_eq!(evm.gas.refunded(), refund);
+            assert_eq!(evm.gas_report.total().refunded(), refund);
+        }
+    }
+
+    #[test]
+    fn test_call_gas_report() {
+        // the callee fails, consuming all the gas it was given
+        let caller = call_code(OpcodeId::CALL, 0, 0);
+        let call_pc = caller.len() - 1;
+        let evm = run_call(&caller, &[0xfe]);
+        assert_eq!(evm.halt_reason, Some(HaltReason::Stop));
+
+        let charges = evm.gas_report.at_pc(call_pc);
+        assert_eq!(charges.get(GasCategory::Base), GasCost::WARM_ACCESS);
+        assert_eq!(
+            charges.get(GasCategory::ColdAccess),
+            GasCost::COLD_ACCOUNT_ACCESS - GasCost::WARM_ACCESS
+        );
+        let forwarded = charges.get(GasCategory::Exception);
+        assert!(forwarded > GasCost::ZERO);
+        assert_eq!(
+            charges.total(),
+            GasCost::COLD_ACCOUNT_ACCESS + forwarded.as_u64()
+        );
+        assert_eq!(evm.gas_report.total().total(), evm.gas.used());
+        let by_pc = evm
+            .gas_report
+            .by_pc()
+            .map(|(_, charges)| charges.total().as_u64());
+        assert_eq!(by_pc.sum::<u64>(), evm.gas.used().as_u64());
+    }
+
+    #[test]
+    fn test_call_gas_report_stipend() {
+        // the charges of value calls add up to the gas used, whatever the callee does
+        let sstore = code(&[
+            (OpcodeId::PUSH1, Some(1)),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::SSTORE, None),
+        ]);
+        for callee in [vec![], vec![0xfe], sstore] {
+            let mut env = Env::default();
+            env.tx.address = address(CALLER);
+            let mut evm = Evm::new(env, 100_000);
+            evm.bytecode = Bytecode::from(&call_code(OpcodeId::CALL, 10, 0)[..]);
+            evm.world.account_mut(address(CALLER)).balance = U256::from(10);
+            evm.world.account_mut(address(CALLEE)).code = Bytecode::from(&callee[..]);
+            let outcome = evm.run(100);
+            assert_eq!(outcome.halt_reason, HaltReason::Stop);
+            assert_eq!(evm.gas_report.total().total(), outcome.gas_used);
+        }
+
+        // a precompile, and a call failing before running the callee
+        for (n, balance) in [(4, 10), (CALLEE, 0)] {
+            let mut env = Env::default();
+            env.tx.address = address(CALLER);
+            let mut evm = Evm::new(env, 100_000);
+            let mut bytecode = call_code(OpcodeId::CALL, 10, 0);
+            // the address pushed before GAS and CALL
+            let at = bytecode.len() - 3;
+            bytecode[at] = n as u8;
+            evm.bytecode = Bytecode::from(&bytecode[..]);
+            evm.world.account_mut(address(CALLER)).balance = U256::from(balance);
+            let outcome = evm.run(100);
+            assert_eq!(outcome.halt_reason, HaltReason::Stop);
+            assert_eq!(evm.gas_report.total().total(), outcome.gas_used);
+        }
+    }
+
+    #[test]
+    fn test_call_context() {
+        // the callee stores its caller and reads the value
+        let callee = code(&[
+            (OpcodeId::CALLER, None),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::SSTORE, None),
+            (OpcodeId::CALLVALUE, None),
+        ]);
+        let caller_address = address_to_word(address(CALLER));
+        let tx_caller = address_to_word(Address::repeat_byte(0x01));
+
+        let mut evm = run_call(&call_code(OpcodeId::CALL, 0, 0), &callee);
+        assert_eq!(slot_0(&mut evm, CALLEE), caller_address);
+        assert_eq!(slot_0(&mut evm, CALLER), U256::zero());
+
+        // CALLCODE runs against the storage of the caller
+        let mut evm = run_call(&call_code(OpcodeId::CALLCODE, 0, 0), &callee);
+        assert_eq!(slot_0(&mut evm, CALLEE), U256::zero());
+        assert_eq!(slot_0(&mut evm, CALLER), caller_address);
+
+        // DELEGATECALL keeps the caller and the value as well
+        let mut evm = run_call(&call_code(OpcodeId::DELEGATECALL, 0, 0), &callee);
+        assert_eq!(evm.stack.inner, vec![U256::one()]);
+        assert_eq!(slot_0(&mut evm, CALLEE), U256::zero());
+        assert_eq!(slot_0(&mut evm, CALLER), tx_caller);
+    }
+
+    #[test]
+    fn test_call_value() {
+        let mut env = Env::default();
+        env.tx.address = address(CALLER);
+        let mut evm = Evm::new(env, 100_000);
+        evm.bytecode = Bytecode::from(&call_code(OpcodeId::CALL, 10, 0)[..]);
+        evm.world.account_mut(evm.context.address).balance = U256::from(15);
+        // one step for every push and GAS, then the call into the empty, cold account
+        for _ in 0..7 {
+            evm.step().unwrap();
+        }
+        let left = evm.gas.left().as_u64()
+            - (GasCost::COLD_ACCOUNT_ACCESS + GasCost::CALL_WITH_VALUE + GasCost::NEW_ACCOUNT)
+                .as_u64();
+        evm.step().unwrap();
+        assert_eq!(evm.depth(), 1);
+        // the callee gets the stipend on top of the forwarded gas
+        assert_eq!(
+            evm.gas.limit(),
+            GasCost::from(left - left / 64) + GasCost::CALL_STIPEND
+        );
+        assert_eq!(evm.world.balance(address(CALLEE)), 10.into());
+        evm.step().unwrap();
+        assert_eq!(evm.depth(), 0);
+        assert_eq!(evm.stack.inner, vec![U256::one()]);
+        assert_eq!(evm.world.balance(evm.context.address), 5.into());
+
+        // not enough balance left, the call fails without running the callee
+        evm.bytecode = Bytecode::from(&call_code(OpcodeId::CALL, 10, 0)[..]);
+        evm.program_counter = 0;
+        for _ in 0..8 {
+            evm.step().unwrap();
+        }
+        assert_eq!(evm.depth(), 0);
+        assert_eq!(evm.stack.inner, vec![U256::one(), U256::zero()]);
+        assert_eq!(evm.world.balance(evm.context.address), 5.into());
+    }
+
+    #[test]
+    fn test_call_new_account() {
+        let gas_used = |spec: SpecId, callee: Option<Account>| {
+            let mut evm = Evm::new(Env::default(), 100_000).with_spec(spec);
+            evm.bytecode = Bytecode::from(&call_code(OpcodeId::CALL, 0, 0)[..]);
+            if let Some(account) = callee {
+                evm.world.insert_account(address(CALLEE), account);
+            }
+            evm.run(100).gas_used.as_u64()
+        };
+        // six pushes and GAS before the call
+        let setup = GasCost::FASTEST.as_u64() * 6 + GasCost::QUICK.as_u64();
+        let call = setup + GasCost::CALL_TANGERINE.as_u64();
+        // before Spurious Dragon, calling an account which does not exist creates it
+        assert_eq!(
+            gas_used(SpecId::Tangerine, None),
+            call + GasCost::NEW_ACCOUNT.as_u64()
+        );
+        assert_eq!(gas_used(SpecId::Tangerine, Some(Account::default())), call);
+        // only sending value does from Spurious Dragon
+        assert_eq!(gas_used(SpecId::SpuriousDragon, None), call);
+    }
+
+    #[test]
+    fn test_call_gas_forwarding() {
+        // the callee stores the gas it got
+        let callee = code(&[
+            (OpcodeId::GAS, None),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::SSTORE, None),
+        ]);
+        let caller = |opcode: OpcodeId, gas: [u8; 3]| {
+            let mut caller = vec![];
+            let n_args = if opcode == OpcodeId::CALL { 5 } else { 4 };
+            for _ in 0..n_args {
+                caller.extend([OpcodeId::PUSH1.as_u8(), 0]);
+            }
+            caller.extend([OpcodeId::PUSH1.as_u8(), CALLEE as u8]);
+            caller.push(OpcodeId::PUSH3.as_u8());
+            caller.extend(gas);
+            caller.push(opcode.as_u8());
+            caller
+        };
+
+        // ask for more than all but one 64th
+        let mut evm = run_call(&caller(OpcodeId::STATICCALL, [0xff; 3]), &callee);
+        // the callee failed, writing in a static context
+        assert_eq!(evm.stack.inner, vec![U256::zero()]);
+        assert_eq!(slot_0(&mut evm, CALLEE), U256::zero());
+        // and consumed all the gas it got
+        let left =
+            1_000_000 - GasCost::FASTEST.as_u64() * 6 - GasCost::COLD_ACCOUNT_ACCESS.as_u64();
+        assert_eq!(evm.gas.left().as_u64(), left / 64);
+
+        // ask for less than all but one 64th
+        let mut evm = run_call(&caller(OpcodeId::CALL, [0x00, 0x75, 0x30]), &callee);
+        assert_eq!(evm.stack.inner, vec![U256::one()]);
+        assert_eq!(
+            slot_0(&mut evm, CALLEE),
+            U256::from(30_000 - GasCost::QUICK.as_u64())
+        );
+
+        // before Tangerine, the callee can get all the gas left
+        let left = 1_000_000 - GasCost::FASTEST.as_u64() * 7 - GasCost::CALL_FRONTIER.as_u64();
+        let gas = (left as u32).to_be_bytes();
+        let mut evm = run_call_in(
+            SpecId::Homestead,
+            &caller(OpcodeId::CALL, [gas[1], gas[2], gas[3]]),
+            &callee,
+        );
+        assert_eq!(evm.stack.inner, vec![U256::one()]);
+        assert_eq!(
+            slot_0(&mut evm, CALLEE),
+            U256::from(left - GasCost::QUICK.as_u64())
+        );
+        // and asking for more runs out of gas
+        let evm = run_call_in(
+            SpecId::Homestead,
+            &caller(OpcodeId::CALL, [0xff; 3]),
+            &callee,
+        );
+        assert!(matches!(
+            evm.halt_reason,
+            Some(HaltReason::Exception(ExecutionError {
+                opcode: OpcodeId::CALL,
+                error: EvmError::OutOfGas { .. },
+                ..
+            }))
+        ));
+        assert_eq!(evm.gas.left(), GasCost::ZERO);
+    }
+
+    #[test]
+    fn test_call_cold_account() {
+        let gas_used = |env: Env, spec: SpecId| {
+            let mut evm = Evm::new(env, 100_000).with_spec(spec);
+            evm.bytecode = Bytecode::from(&call_code(OpcodeId::STATICCALL, 0, 0)[..]);
+            evm.run(100).gas_used.as_u64()
+        };
+        // five pushes and GAS before the call
+        let setup = GasCost::FASTEST.as_u64() * 5 + GasCost::QUICK.as_u64();
+        assert_eq!(
+            gas_used(Env::default(), SpecId::Cancun),
+            setup + GasCost::COLD_ACCOUNT_ACCESS.as_u64()
+        );
+        assert_eq!(
+            gas_used(Env::default(), SpecId::Istanbul),
+            setup + GasCost::CALL_TANGERINE.as_u64()
+        );
+        // warm from the start through the access list of the transaction
+        let mut env = Env::default();
+        env.tx.access_list = vec![AccessListItem {
+            address: address(CALLEE),
+            storage_keys: Vec::new(),
+        }];
+        assert_eq!(
+            gas_used(env, SpecId::Cancun),
+            setup + GasCost::WARM_ACCESS.as_u64()
+        );
+
+        // the second call is warm
+        let mut code = call_code(OpcodeId::STATICCALL, 0, 0);
+        code.extend(call_code(OpcodeId::STATICCALL, 0, 0));
+        let mut evm = Evm::new(Env::default(), 100_000);
+        evm.bytecode = Bytecode::from(&code[..]);
+        assert_eq!(
+            evm.run(100).gas_used.as_u64(),
+            2 * setup + (GasCost::COLD_ACCOUNT_ACCESS + GasCost::WARM_ACCESS).as_u64()
+        );
+    }
+
+    #[test]
+    fn test_staticcall() {
+        // reading is allowed
+        let callee = code(&[(OpcodeId::PUSH1, Some(0)), (OpcodeId::SLOAD, None)]);
+        let evm = run_call(&call_code(OpcodeId::STATICCALL, 0, 0), &callee);
+        assert_eq!(evm.stack.inner, vec![U256::one()]);
+
+        for callee in [
+            code(&[
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::LOG0, None),
+            ]),
+            code(&[
+                (OpcodeId::PUSH1, Some(1)),
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::TSTORE, None),
+            ]),
+            code(&[
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::CREATE, None),
+            ]),
+            // nested calls inherit the static context
+            call_code(OpcodeId::CALL, 1, 0),
+        ] {
+            let evm = run_call(&call_code(OpcodeId::STATICCALL, 0, 0), &callee);
+            assert_eq!(evm.halt_reason, Some(HaltReason::Stop));
+            assert_eq!(evm.stack.inner, vec![U256::zero()]);
+            assert!(evm.logs.is_empty());
+        }
+        let mut evm = Evm::new(Env::default(), 1000);
+        evm.context.is_static = true;
+        evm.bytecode = Bytecode::from(&call_code(OpcodeId::CALL, 1, 0)[..]);
+        assert_eq!(
+            evm.run(10).halt_reason.error(),
+            Some(&EvmError::WriteProtection)
+        );
+    }
+
+    #[test]
+    fn test_returndatacopy() {
+        let callee = code(&[
+            (OpcodeId::PUSH1, Some(0x2a)),
+            (OpcodeId::PUSH1, Some(31)),
+            (OpcodeId::MSTORE8, None),
+            (OpcodeId::PUSH1, Some(32)),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::RETURN, None),
+        ]);
+        let copy = |offset, size| {
+            let mut caller = call_code(OpcodeId::CALL, 0, 0);
+            caller.extend(code(&[
+                (OpcodeId::PUSH1, Some(size)),
+                (OpcodeId::PUSH1, Some(offset)),
+                (OpcodeId::PUSH1, Some(32)),
+                (OpcodeId::RETURNDATACOPY, None),
+            ]));
+            run_call(&caller, &callee)
+        };
+        let evm = copy(31, 1);
+        assert_eq!(evm.halt_reason, Some(HaltReason::Stop));
+        assert_eq!(evm.memory.raw_slice(32, 2), &[0x2a, 0]);
+
+        let evm = copy(1, 32);
+        assert_eq!(
+            evm.halt_reason.as_ref().and_then(HaltReason::error),
+            Some(&EvmError::ReturnDataOutOfBounds {
+                offset: U256::one(),
+                size: U256::from(32),
+                available: 32
+            })
+        );
+        // an empty copy at the end is fine
+        let evm = copy(32, 0);
+        assert_eq!(evm.halt_reason, Some(HaltReason::Stop));
+    }
+
+    #[test]
+    fn test_call_depth() {
+        // the callee calls itself until the depth limit
+        let mut evm = Evm::new(Env::default(), 100_000_000_000_000);
+        let code = call_code(OpcodeId::CALL, 0, 0);
+        evm.bytecode = Bytecode::from(&code[..]);
+        evm.world.account_mut(address(CALLEE)).code = Bytecode::from(&code[..]);
+        let mut max_depth = 0;
+        while evm.halt_reason.is_none() {
+            evm.step().unwrap();
+            max_depth = max_depth.max(evm.depth());
+        }
+        assert_eq!(max_depth, MAX_CALL_DEPTH);
+        assert_eq!(evm.halt_reason, Some(HaltReason::Stop));
+        assert_eq!(evm.stack.inner, vec![U256::one()]);
+    }
+
+    #[test]
+    fn test_call_precompile() {
+        // copies a word through the identity precompile, sending `gas`
+        let identity = |gas| {
+            let caller = code(&[
+                (OpcodeId::PUSH1, Some(0x2a)),
+                (OpcodeId::PUSH1, Some(31)),
+                (OpcodeId::MSTORE8, None),
+                (OpcodeId::PUSH1, Some(32)),
+                (OpcodeId::PUSH1, Some(32)),
+                (OpcodeId::PUSH1, Some(32)),
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::PUSH1, Some(0x04)),
+                (OpcodeId::PUSH1, Some(gas)),
+                (OpcodeId::STATICCALL, None),
+            ]);
+            run_call(&caller, &[])
+        };
+        let evm = identity(18);
+        assert_eq!(evm.halt_reason, Some(HaltReason::Stop));
+        assert!(evm.frames.is_empty());
+        assert_eq!(evm.stack.inner, vec![U256::one()]);
+        assert_eq!(evm.memory.raw_get(32), U256::from(0x2a));
+        assert_eq!(evm.return_data_buffer, evm.memory.raw_slice(0, 32));
+        // unused gas is given back
+        assert_eq!(identity(100).gas.left(), evm.gas.left());
+
+        // not enough gas, all of it is consumed
+        let failed = identity(17);
+        assert_eq!(failed.stack.inner, vec![U256::zero()]);
+        assert!(failed.return_data_buffer.is_empty());
+        assert_eq!(failed.memory.raw_get(32), U256::zero());
+        assert_eq!(failed.gas.left() + 17, evm.gas.left() + 18);
+    }
+
+    /// Init code deploying the 10-byte runtime of `test_call_returns_data`
+    fn init_code() -> Vec<u8> {
+        let runtime = code(&[
+            (OpcodeId::PUSH1, Some(0x2a)),
+            (OpcodeId::PUSH1, Some(31)),
+            (OpcodeId::MSTORE8, None),
+            (OpcodeId::PUSH1, Some(32)),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::RETURN, None),
+        ]);
+        let mut init_code = code(&[
+            (OpcodeId::PUSH1, Some(runtime.len() as u8)),
+            (OpcodeId::PUSH1, Some(12)),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::CODECOPY, None),
+            (OpcodeId::PUSH1, Some(runtime.len() as u8)),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::RETURN, None),
+        ]);
+        assert_eq!(init_code.len(), 12);
+        init_code.extend(runtime);
+        init_code
+    }
+
+    /// Builds an evm at `CALLER` running `CREATE` or `CREATE2` with zero salt and value
+    /// on `init_code` in memory, followed by `code`
+    fn create_evm(opcode: OpcodeId, init_code: &[u8], code: &[u8]) -> Evm {
+        let mut env = Env::default();
+        env.tx.address = address(CALLER);
+        let mut evm = Evm::new(env, 10_000_000);
+        let size = init_code.len() as u16;
+        if !init_code.is_empty() {
+            evm.memory
+                .copy_from(
+                    init_code,
+                    U256::zero(),
+                    U256::zero(),
+                    U256::from(size),
+                    GasCost::ZERO,
+                    &mut evm.gas,
+                )
+                .unwrap();
+        }
+        let mut bytecode = vec![];
+        if opcode == OpcodeId::CREATE2 {
+            bytecode.extend([OpcodeId::PUSH1.as_u8(), 0]);
+        }
+        bytecode.push(OpcodeId::PUSH2.as_u8());
+        bytecode.extend(size.to_be_bytes());
+        bytecode.extend([OpcodeId::PUSH1.as_u8(), 0, OpcodeId::PUSH1.as_u8(), 0]);
+        bytecode.push(opcode.as_u8());
+        bytecode.extend(code);
+        evm.bytecode = Bytecode::from(&bytecode[..]);
+        evm
+    }
+
+    #[test]
+    fn test_create() {
+        let init_code = init_code();
+        for opcode in [OpcodeId::CREATE, OpcodeId::CREATE2] {
+            // call the new contract, copying its return data to memory
+            let mut evm = create_evm(
+                opcode,
+                &init_code,
+                &code(&[
+                    (OpcodeId::PUSH1, Some(32)),
+                    (OpcodeId::PUSH1, Some(0)),
+                    (OpcodeId::PUSH1, Some(0)),
+                    (OpcodeId::PUSH1, Some(0)),
+                    (OpcodeId::PUSH1, Some(0)),
+                    (OpcodeId::DUP6, None),
+                    (OpcodeId::GAS, None),
+                    (OpcodeId::CALL, None),
+                ]),
+            );
+            assert_eq!(evm.run(100).halt_reason, HaltReason::Stop);
+            let created = match opcode {
+                OpcodeId::CREATE => create_address(address(CALLER), 0),
+                _ => create2_address(address(CALLER), U256::zero(), &init_code),
+            };
+            assert_eq!(evm.stack.inner, vec![address_to_word(created), U256::one()]);
+            assert_eq!(evm.memory.raw_get(0), U256::from(0x2a));
+            let account = evm.world.account(created).unwrap();
+            assert_eq!(account.code.to_bytes(), &init_code[12..]);
+            assert_eq!(account.nonce, 1);
+            assert_eq!(evm.world.account(address(CALLER)).unwrap().nonce, 1);
+        }
+    }
+
+    #[test]
+    fn test_create_gas() {
+        let mut evm = create_evm(OpcodeId::CREATE, &init_code(), &[]);
+        let gas_used = evm.gas.used();
+        evm.run(100);
+        let init_code_gas = GasCost::FASTEST.as_u64() * 5
+            + GasCost::FASTEST.as_u64()
+            + GasCost::COPY.as_u64()
+            + Memory::gas_cost(1).as_u64();
+        let deposit_gas = GasCost::CODE_DEPOSIT_BYTE_COST.as_u64() * 10;
+        assert_eq!(
+            evm.gas.used() - gas_used,
+            GasCost::FASTEST
+                + GasCost::FASTEST
+                + GasCost::FASTEST
+                + GasCost::CREATE
+                + GasCost::INITCODE_WORD_COST
+                + init_code_gas
+                + deposit_gas
+        );
+
+        // CREATE2 pays for hashing the init code
+        let mut evm = create_evm(OpcodeId::CREATE2, &init_code(), &[]);
+        let gas_used = evm.gas.used();
+        evm.run(100);
+        assert_eq!(
+            evm.gas.used() - gas_used,
+            GasCost::FASTEST
+                + GasCost::FASTEST
+                + GasCost::FASTEST
+                + GasCost::FASTEST
+                + GasCost::CREATE
+                + GasCost::INITCODE_WORD_COST
+                + GasCost::COPY_SHA3
+                + init_code_gas
+                + deposit_gas
+        );
+    }
+
+    #[test]
+    fn test_create_gas_forwarding() {
+        // the init code deploys the gas it got as its code
+        let init_code = code(&[
+            (OpcodeId::GAS, None),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::MSTORE, None),
+            (OpcodeId::PUSH1, Some(32)),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::RETURN, None),
+        ]);
+        let init_gas = |spec: SpecId| {
+            let mut evm = create_evm(OpcodeId::CREATE, &init_code, &[]).with_spec(spec);
+            evm.run(100);
+            let created = create_address(address(CALLER), 0);
+            U256::from_big_endian(&evm.world.code(created).to_bytes()).as_u64()
+        };
+        // before Tangerine, the init code gets all the gas left
+        let left = init_gas(SpecId::Homestead) + GasCost::QUICK.as_u64();
+        assert_eq!(
+            init_gas(SpecId::Tangerine),
+            left - left / 64 - GasCost::QUICK.as_u64()
+        );
+    }
+
+    #[test]
+    fn test_create_failures() {
+        // reverting init code, its output is the return data
+        let init_code = code(&[
+            (OpcodeId::PUSH1, Some(1)),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::REVERT, None),
+        ]);
+        let mut evm = create_evm(OpcodeId::CREATE, &init_code, &[]);
+        evm.run(100);
+        assert_eq!(evm.stack.inner, vec![U256::zero()]);
+        assert_eq!(evm.return_data_buffer, vec![0]);
+        // the nonce is used anyway
+        assert_eq!(evm.world.account(address(CALLER)).unwrap().nonce, 1);
+        assert!(evm
+            .world
+            .account(create_address(address(CALLER), 0))
+            .is_none());
+
+        // deployed code of MAX_CODE_SIZE zero bytes, then one byte more than EIP-170 allows
+        for (size, created) in [
+            (MAX_CODE_SIZE as u16, true),
+            (MAX_CODE_SIZE as u16 + 1, false),
+        ] {
+            let mut init_code = vec![OpcodeId::PUSH2.as_u8()];
+            init_code.extend(size.to_be_bytes());
+            init_code.extend(code(&[
+                (OpcodeId::PUSH1, Some(0)),
+                (OpcodeId::RETURN, None),
+            ]));
+            let mut evm = create_evm(OpcodeId::CREATE, &init_code, &[]);
+            evm.run(100);
+            assert_eq!(evm.halt_reason, Some(HaltReason::Stop));
+            assert_eq!(evm.stack.inner[0].is_zero(), !created);
+        }
+        // no limit before Spurious Dragon
+        let size = MAX_CODE_SIZE as u16 + 1;
+        let mut init_code = vec![OpcodeId::PUSH2.as_u8()];
+        init_code.extend(size.to_be_bytes());
+        init_code.extend(code(&[
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::RETURN, None),
+        ]));
+        let mut evm = create_evm(OpcodeId::CREATE, &init_code, &[]).with_spec(SpecId::Tangerine);
+        evm.run(100);
+        let created = create_address(address(CALLER), 0);
+        assert_eq!(evm.stack.inner, vec![address_to_word(created)]);
+        assert_eq!(evm.world.code(created).inner.len(), size as usize);
+        // and contract accounts start with nonce 0
+        assert_eq!(evm.world.account(created).unwrap().nonce, 0);
+
+        // deployed code starting with 0xef, rejected from London by EIP-3541
+        let init_code = code(&[
+            (OpcodeId::PUSH1, Some(0xef)),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::MSTORE8, None),
+            (OpcodeId::PUSH1, Some(1)),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::RETURN, None),
+        ]);
+        for (spec, created) in [(SpecId::London, false), (SpecId::Berlin, true)] {
+            let mut evm = create_evm(OpcodeId::CREATE, &init_code, &[]).with_spec(spec);
+            evm.run(100);
+            assert_eq!(evm.stack.inner[0].is_zero(), !created, "{spec:?}");
+        }
+
+        // init code larger than EIP-3860 allows
+        let mut evm = create_evm(OpcodeId::CREATE, &[0; MAX_INITCODE_SIZE + 1], &[]);
+        assert_eq!(
+            evm.run(100).halt_reason.error(),
+            Some(&EvmError::InitCodeSizeExceeded)
+        );
+        // but not before Shanghai, the zero init code just stops
+        let mut evm =
+            create_evm(OpcodeId::CREATE, &[0; MAX_INITCODE_SIZE + 1], &[]).with_spec(SpecId::Merge);
+        assert_eq!(evm.run(100).halt_reason, HaltReason::Stop);
+        assert_ne!(evm.stack.inner, vec![U256::zero()]);
+
+        // creating twice at the same address
+        let mut evm = create_evm(OpcodeId::CREATE2, &[], &[]);
+        let bytecode = evm.bytecode.to_bytes();
+        evm.bytecode = Bytecode::from(&[&bytecode[..], &bytecode[..]].concat()[..]);
+        evm.run(100);
+        assert_eq!(
+            evm.stack.inner,
+            vec![
+                address_to_word(create2_address(address(CALLER), U256::zero(), &[])),
+                U256::zero()
+            ]
+        );
+    }
+}
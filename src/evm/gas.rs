@@ -1,5 +1,12 @@
-use crate::evm::EvmError;
+use crate::evm::{EvmError, SpecId};
+use std::collections::BTreeMap;
 use std::ops::{Add, AddAssign, Sub};
+use strum::{EnumCount, EnumIter, IntoEnumIterator};
+
+/// At most one fifth of the gas used is refunded, EIP-3529
+pub const MAX_REFUND_QUOTIENT: u64 = 5;
+/// At most half of the gas used is refunded before EIP-3529
+pub const MAX_REFUND_QUOTIENT_FRONTIER: u64 = 2;
 
 /// Defines the gas consumption.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord)]
@@ -8,8 +15,8 @@ pub struct GasCost(u64);
 impl GasCost {
     /// Constant cost for free step
     pub const ZERO: Self = Self(0);
-    // /// Constant cost for jumpdest step, only takes one gas
-    // pub const ONE: Self = Self(1);
+    /// Constant cost for jumpdest step, only takes one gas
+    pub const ONE: Self = Self(1);
     /// Constant cost for quick step
     pub const QUICK: Self = Self(2);
     /// Constant cost for fastest step
@@ -26,8 +33,10 @@ impl GasCost {
     pub const SHA3: Self = Self(30);
     // /// Constant cost for SELFDESTRUCT
     // pub const SELFDESTRUCT: Self = Self(5000);
-    // /// Constant cost for CREATE and CREATE2
-    // pub const CREATE: Self = Self(32000);
+    /// Constant cost for CREATE and CREATE2
+    pub const CREATE: Self = Self(32000);
+    /// Cost for every word of init code, EIP-3860
+    pub const INITCODE_WORD_COST: Self = Self(2);
     /// Constant cost for copying every word
     pub const COPY: Self = Self(3);
     /// Constant cost for copying every word, specifically in the case of SHA3
@@ -35,71 +44,122 @@ impl GasCost {
     pub const COPY_SHA3: Self = Self(6);
     /// Constant cost for accessing account or storage key
     pub const WARM_ACCESS: Self = Self(100);
+    /// Constant cost for SLOAD before EIP-150
+    pub const SLOAD_FRONTIER: Self = Self(50);
+    /// Constant cost for SLOAD from EIP-150
+    pub const SLOAD_TANGERINE: Self = Self(200);
+    /// Constant cost for SLOAD from EIP-1884, also the cost of a no-op SSTORE
+    /// from EIP-2200
+    pub const SLOAD_ISTANBUL: Self = Self(800);
     /// Constant cost for a cold SLOAD
     pub const COLD_SLOAD: Self = Self(2100);
-    // /// Constant cost for a cold account access
-    // pub const COLD_ACCOUNT_ACCESS: Self = Self(2600);
+    /// Constant cost for a cold account access
+    pub const COLD_ACCOUNT_ACCESS: Self = Self(2600);
     /// SSTORE reentrancy sentry
     pub const SSTORE_SENTRY: Self = Self(2300);
     /// Constant cost for a storage set
     pub const SSTORE_SET: Self = Self(20000);
     /// Constant cost for a storage reset
     pub const SSTORE_RESET: Self = Self(2900);
+    /// Constant cost for a storage reset before EIP-2929 took the cold SLOAD out of
+    /// it
+    pub const SSTORE_RESET_FRONTIER: Self = Self(5000);
     /// Constant cost for a storage clear. EIP-3529 changed it to 4800 from
     /// 15000.
     pub const SSTORE_CLEARS_SCHEDULE: Self = Self(4800);
-    // /// Constant cost for a non-creation transaction
-    // pub const TX: Self = Self(21000);
-    // /// Constant cost for a creation transaction
-    // pub const CREATION_TX: Self = Self(53000);
-    // /// Constant cost for calling with non-zero value
-    // pub const CALL_WITH_VALUE: Self = Self(9000);
-    // /// Constant cost for turning empty account into non-empty account
-    // pub const NEW_ACCOUNT: Self = Self(25000);
-    // /// Cost per byte of deploying a new contract
-    // pub const CODE_DEPOSIT_BYTE_COST: Self = Self(200);
+    /// Refund for a storage clear before EIP-3529
+    pub const SSTORE_CLEARS_SCHEDULE_FRONTIER: Self = Self(15000);
+    /// Constant cost for a non-creation transaction
+    pub const TX: Self = Self(21000);
+    /// Constant cost for a creation transaction, EIP-2
+    pub const CREATION_TX: Self = Self(53000);
+    /// Cost for every zero byte of transaction data
+    pub const TX_DATA_ZERO: Self = Self(4);
+    /// Cost for every non-zero byte of transaction data, EIP-2028 changed it
+    /// from 68 to 16.
+    pub const TX_DATA_NON_ZERO: Self = Self(16);
+    /// Cost for every non-zero byte of transaction data before EIP-2028
+    pub const TX_DATA_NON_ZERO_FRONTIER: Self = Self(68);
+    /// Cost for every address in the access list of a transaction, EIP-2930
+    pub const ACCESS_LIST_ADDRESS: Self = Self(2400);
+    /// Cost for every storage key in the access list of a transaction, EIP-2930
+    pub const ACCESS_LIST_STORAGE_KEY: Self = Self(1900);
+    /// Constant cost for the CALL family before EIP-150
+    pub const CALL_FRONTIER: Self = Self(40);
+    /// Constant cost for the CALL family from EIP-150 until EIP-2929
+    pub const CALL_TANGERINE: Self = Self(700);
+    /// Constant cost for calling with non-zero value
+    pub const CALL_WITH_VALUE: Self = Self(9000);
+    /// Free gas given to the callee of a call with non-zero value
+    pub const CALL_STIPEND: Self = Self(2300);
+    /// Constant cost for turning empty account into non-empty account
+    pub const NEW_ACCOUNT: Self = Self(25000);
+    /// Cost per byte of deploying a new contract
+    pub const CODE_DEPOSIT_BYTE_COST: Self = Self(200);
     /// Denominator of quadratic part of memory expansion gas cost
     pub const MEMORY_EXPANSION_QUAD_DENOMINATOR: Self = Self(512);
     /// Coefficient of linear part of memory expansion gas cost
     pub const MEMORY_EXPANSION_LINEAR_COEFF: Self = Self(3);
-    // /// Constant gas for LOG[0-4] op codes
-    // pub const LOG: Self = Self(375);
+    /// Constant gas for LOG[0-4] op codes
+    pub const LOG: Self = Self(375);
+    /// Gas for every topic of LOG[0-4] op codes
+    pub const LOG_TOPIC: Self = Self(375);
+    /// Gas for every byte of data of LOG[0-4] op codes
+    pub const LOG_DATA_BYTE: Self = Self(8);
     /// Times ceil exponent byte size for the EXP instruction, EIP-158 changed
     /// it from 10 to 50.
     pub const EXP_BYTE_TIMES: Self = Self(50);
-    // /// Base gas cost for precompile call: Elliptic curve recover
-    // pub const PRECOMPILE_ECRECOVER_BASE: Self = Self(3_000);
-    // /// Base gas cost for precompile call: SHA256
-    // pub const PRECOMPILE_SHA256_BASE: Self = Self(60);
-    // /// Per-word gas cost for SHA256
-    // pub const PRECOMPILE_SHA256_PER_WORD: Self = Self(12);
-    // /// Base gas cost for precompile call: RIPEMD160
-    // pub const PRECOMPILE_RIPEMD160_BASE: Self = Self(600);
-    // /// Per-word gas cost for RIPEMD160
-    // pub const PRECOMPILE_RIPEMD160_PER_WORD: Self = Self(120);
-    // /// Base gas cost for precompile call: Identity
-    // pub const PRECOMPILE_IDENTITY_BASE: Self = Self(15);
-    // /// Per-word gas cost for Identity
-    // pub const PRECOMPILE_IDENTITY_PER_WORD: Self = Self(3);
-    // /// Base gas cost for precompile call: BN256 point addition
-    // pub const PRECOMPILE_BN256ADD: Self = Self(150);
-    // /// Base gas cost for precompile call: BN256 scalar multiplication
-    // pub const PRECOMPILE_BN256MUL: Self = Self(6_000);
-    // /// Base gas cost for precompile call: BN256 pairing op base cost
-    // pub const PRECOMPILE_BN256PAIRING: Self = Self(45_000);
-    // /// Per-pair gas cost for BN256 pairing
-    // pub const PRECOMPILE_BN256PAIRING_PER_PAIR: Self = Self(34_000);
-    // /// Base gas cost for precompile call: MODEXP
-    // pub const PRECOMPILE_MODEXP: Self = Self(0);
-    // /// Minimum gas cost for precompile calls: MODEXP
-    // pub const PRECOMPILE_MODEXP_MIN: Self = Self(200);
-    // /// Base gas cost for precompile call: BLAKE2F
-    // pub const PRECOMPILE_BLAKE2F: Self = Self(0);
+    /// Times ceil exponent byte size for the EXP instruction before EIP-158
+    pub const EXP_BYTE_TIMES_FRONTIER: Self = Self(10);
+    /// Base gas cost for precompile call: Elliptic curve recover
+    pub const PRECOMPILE_ECRECOVER_BASE: Self = Self(3_000);
+    /// Base gas cost for precompile call: SHA256
+    pub const PRECOMPILE_SHA256_BASE: Self = Self(60);
+    /// Per-word gas cost for SHA256
+    pub const PRECOMPILE_SHA256_PER_WORD: Self = Self(12);
+    /// Base gas cost for precompile call: RIPEMD160
+    pub const PRECOMPILE_RIPEMD160_BASE: Self = Self(600);
+    /// Per-word gas cost for RIPEMD160
+    pub const PRECOMPILE_RIPEMD160_PER_WORD: Self = Self(120);
+    /// Base gas cost for precompile call: Identity
+    pub const PRECOMPILE_IDENTITY_BASE: Self = Self(15);
+    /// Per-word gas cost for Identity
+    pub const PRECOMPILE_IDENTITY_PER_WORD: Self = Self(3);
+    /// Base gas cost for precompile call: BN256 point addition, EIP-1108
+    pub const PRECOMPILE_BN256ADD: Self = Self(150);
+    /// Base gas cost for precompile call: BN256 point addition before Istanbul
+    pub const PRECOMPILE_BN256ADD_BYZANTIUM: Self = Self(500);
+    /// Base gas cost for precompile call: BN256 scalar multiplication, EIP-1108
+    pub const PRECOMPILE_BN256MUL: Self = Self(6_000);
+    /// Base gas cost for precompile call: BN256 scalar multiplication before Istanbul
+    pub const PRECOMPILE_BN256MUL_BYZANTIUM: Self = Self(40_000);
+    /// Base gas cost for precompile call: BN256 pairing op base cost, EIP-1108
+    pub const PRECOMPILE_BN256PAIRING: Self = Self(45_000);
+    /// Base gas cost for precompile call: BN256 pairing op base cost before Istanbul
+    pub const PRECOMPILE_BN256PAIRING_BYZANTIUM: Self = Self(100_000);
+    /// Per-pair gas cost for BN256 pairing, EIP-1108
+    pub const PRECOMPILE_BN256PAIRING_PER_PAIR: Self = Self(34_000);
+    /// Per-pair gas cost for BN256 pairing before Istanbul
+    pub const PRECOMPILE_BN256PAIRING_PER_PAIR_BYZANTIUM: Self = Self(80_000);
+    /// Base gas cost for precompile call: MODEXP
+    pub const PRECOMPILE_MODEXP: Self = Self(0);
+    /// Minimum gas cost for precompile calls: MODEXP
+    pub const PRECOMPILE_MODEXP_MIN: Self = Self(200);
+    /// Base gas cost for precompile call: BLAKE2F
+    pub const PRECOMPILE_BLAKE2F: Self = Self(0);
+    /// Per-round gas cost for BLAKE2F
+    pub const PRECOMPILE_BLAKE2F_PER_ROUND: Self = Self(1);
 
     /// Returns the gas cost.
     pub fn as_u64(&self) -> u64 {
         self.0
     }
+
+    /// Returns the gas cost of `size` bytes when every 32-byte word costs `self`,
+    /// e.g. `GasCost::COPY.per_word(size)` for the copy opcodes.
+    pub fn per_word(self, size: usize) -> GasCost {
+        GasCost(self.0.saturating_mul(size.div_ceil(32) as u64))
+    }
 }
 
 impl From<u64> for GasCost {
@@ -152,6 +212,119 @@ impl Sub<u64> for GasCost {
     }
 }
 
+/// What a charge of gas pays for
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, EnumCount, EnumIter)]
+pub enum GasCategory {
+    /// Intrinsic gas of the transaction, charged before any code runs
+    Intrinsic,
+    /// Constant cost of an opcode, and the warm access cost from Berlin
+    Base,
+    /// Memory expansion
+    Memory,
+    /// Surcharge for the first access of an account or a storage slot, EIP-2929
+    ColdAccess,
+    /// Dynamic cost of `SSTORE`
+    StorageWrite,
+    /// Per-word cost of copying, hashing and init code
+    Copy,
+    /// Other dynamic costs: exponent bytes, log topics and data, value transfers,
+    /// new accounts, code deposits and precompiles
+    Dynamic,
+    /// Gas left when an exceptional halt consumed it
+    Exception,
+}
+
+/// Gas charged per category, and the refunds earned alongside
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct GasBreakdown {
+    charged: [u64; GasCategory::COUNT],
+    refunded: i64,
+}
+
+impl GasBreakdown {
+    /// Returns the gas charged under `category`
+    pub fn get(&self, category: GasCategory) -> GasCost {
+        GasCost(self.charged[category as usize])
+    }
+
+    /// Returns the gas charged under all categories
+    pub fn total(&self) -> GasCost {
+        GasCost(self.charged.iter().sum())
+    }
+
+    /// Returns the refunds earned, negative when more were taken back
+    pub fn refunded(&self) -> i64 {
+        self.refunded
+    }
+
+    /// Returns the categories with gas charged, and the gas charged under them
+    pub fn iter(&self) -> impl Iterator<Item = (GasCategory, GasCost)> + '_ {
+        GasCategory::iter()
+            .map(|category| (category, self.get(category)))
+            .filter(|(_, cost)| *cost != GasCost::ZERO)
+    }
+
+    /// Returns the charges without the refunds, e.g. of a callee which failed
+    pub fn without_refunds(self) -> Self {
+        GasBreakdown {
+            refunded: 0,
+            ..self
+        }
+    }
+
+    /// Returns `true` if nothing was charged or refunded
+    pub fn is_empty(&self) -> bool {
+        self.refunded == 0 && self.charged.iter().all(|&cost| cost == 0)
+    }
+}
+
+impl AddAssign<GasBreakdown> for GasBreakdown {
+    fn add_assign(&mut self, rhs: GasBreakdown) {
+        for (charged, rhs) in self.charged.iter_mut().zip(rhs.charged) {
+            *charged += rhs;
+        }
+        self.refunded += rhs.refunded;
+    }
+}
+
+/// Where the gas of an execution went, in total and per instruction
+///
+/// The charges of called contracts are put down to the `CALL` or `CREATE`
+/// of the top level code which made the call, so the charges add up to the gas used.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct GasReport {
+    total: GasBreakdown,
+    by_pc: BTreeMap<usize, GasBreakdown>,
+}
+
+impl GasReport {
+    /// Returns the charges of the whole execution
+    pub fn total(&self) -> &GasBreakdown {
+        &self.total
+    }
+
+    /// Returns the charges of the instruction at `pc`
+    pub fn at_pc(&self, pc: usize) -> GasBreakdown {
+        self.by_pc.get(&pc).copied().unwrap_or_default()
+    }
+
+    /// Returns the charges of every instruction which was charged, by program counter
+    pub fn by_pc(&self) -> impl Iterator<Item = (usize, &GasBreakdown)> {
+        self.by_pc.iter().map(|(pc, breakdown)| (*pc, breakdown))
+    }
+
+    /// Adds `charges` to the total, and to the instruction at `pc` if any
+    pub fn record(&mut self, pc: Option<usize>, charges: GasBreakdown) {
+        if charges.is_empty() {
+            return;
+        }
+        self.total += charges;
+        if let Some(pc) = pc {
+            *self.by_pc.entry(pc).or_default() += charges;
+        }
+    }
+}
+
 /// Gas Manager for EVM
 #[derive(Debug, Clone, Copy)]
 pub struct Gas {
@@ -159,6 +332,11 @@ pub struct Gas {
     limit: GasCost,
     /// Gas used
     used: GasCost,
+    /// Gas refunded by storage clears, negative when the frame took back
+    /// refunds of earlier frames
+    refunded: i64,
+    /// Charges not yet taken by `take_charges`
+    charges: GasBreakdown,
 }
 
 impl Gas {
@@ -167,6 +345,8 @@ impl Gas {
         Gas {
             limit: limit.into(),
             used: 0.into(),
+            refunded: 0,
+            charges: GasBreakdown::default(),
         }
     }
 
@@ -191,16 +371,205 @@ impl Gas {
         if self.left() >= cost {
             Ok(())
         } else {
-            Err(EvmError::OutOfGas)
+            Err(EvmError::out_of_gas(cost, self.left()))
         }
     }
 
-    /// Use the gas
+    /// Use the gas for the constant cost of an opcode
     pub fn use_gas(&mut self, cost: GasCost) -> Result<(), EvmError> {
-        if let Err(e) = self.enough(cost) {
-            return Err(e);
+        self.charge([(GasCategory::Base, cost)])
+    }
+
+    /// Use the gas for every charge of an instruction at once,
+    /// nothing is charged if the sum is not enough
+    pub fn charge<I>(&mut self, charges: I) -> Result<(), EvmError>
+    where
+        I: IntoIterator<Item = (GasCategory, GasCost)>,
+        I::IntoIter: Clone,
+    {
+        let charges = charges.into_iter();
+        let total = charges
+            .clone()
+            .fold(GasCost::ZERO, |total, (_, cost)| total + cost);
+        self.enough(total)?;
+        self.used += total;
+        for (category, cost) in charges {
+            self.record_charge(category, cost);
         }
-        self.used += cost.as_u64();
         Ok(())
     }
+
+    /// Set aside gas for a callee, which charges it under its own categories
+    pub fn forward(&mut self, amount: GasCost) -> Result<(), EvmError> {
+        self.enough(amount)?;
+        self.used += amount;
+        Ok(())
+    }
+
+    /// Put down gas already used, e.g. forwarded gas consumed without running a callee,
+    /// under `category`
+    pub fn record_charge(&mut self, category: GasCategory, cost: GasCost) {
+        self.charges.charged[category as usize] += cost.0;
+    }
+
+    /// Take `cost` put down under `category` back out of the charges,
+    /// e.g. the call stipend, which the callee puts down under its own categories
+    pub fn pass_on(&mut self, category: GasCategory, cost: GasCost) {
+        self.charges.charged[category as usize] -= cost.0;
+    }
+
+    /// Use all the gas left, as exceptional halts do
+    pub fn consume_all(&mut self) {
+        self.record_charge(GasCategory::Exception, self.left());
+        self.used = self.limit;
+    }
+
+    /// Returns the gas refunded so far
+    pub fn refunded(&self) -> i64 {
+        self.refunded
+    }
+
+    /// Record a refund, or take one back when `refund` is negative
+    pub fn record_refund(&mut self, refund: i64) {
+        self.refunded += refund;
+        self.charges.refunded += refund;
+    }
+
+    /// Returns the refund given at the end of the execution under `spec`,
+    /// capped at a share of the gas used
+    pub fn final_refund(&self, spec: SpecId) -> GasCost {
+        let quotient = if spec.is_enabled_in(SpecId::London) {
+            MAX_REFUND_QUOTIENT
+        } else {
+            MAX_REFUND_QUOTIENT_FRONTIER
+        };
+        let refunded = self.refunded.max(0) as u64;
+        GasCost(refunded.min(self.used.0 / quotient))
+    }
+
+    /// Give back gas, e.g. the gas a callee did not use
+    pub fn reclaim(&mut self, amount: GasCost) {
+        self.used = GasCost(self.used.0.saturating_sub(amount.0));
+    }
+
+    /// Returns the charges and refunds recorded since the last call
+    pub fn take_charges(&mut self) -> GasBreakdown {
+        std::mem::take(&mut self.charges)
+    }
+
+    /// Take over the charges and refunds of a callee which returned
+    pub fn absorb(&mut self, charges: GasBreakdown) {
+        self.charges += charges;
+        self.refunded += charges.refunded;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_word() {
+        assert_eq!(GasCost::COPY.per_word(0), GasCost::ZERO);
+        assert_eq!(GasCost::COPY.per_word(1), GasCost::COPY);
+        assert_eq!(GasCost::COPY.per_word(32), GasCost::COPY);
+        assert_eq!(GasCost::COPY.per_word(33), GasCost::from(6));
+        assert_eq!(GasCost::COPY_SHA3.per_word(64), GasCost::from(12));
+    }
+
+    #[test]
+    fn test_reclaim() {
+        let mut gas = Gas::new(100);
+        gas.use_gas(GasCost::from(60)).unwrap();
+        gas.reclaim(GasCost::from(20));
+        assert_eq!(gas.left(), GasCost::from(60));
+        // the stipend can give back more than was used
+        gas.reclaim(GasCost::CALL_STIPEND);
+        assert_eq!(gas.left(), GasCost::from(100));
+    }
+
+    #[test]
+    fn test_final_refund() {
+        let mut gas = Gas::new(100_000);
+        gas.use_gas(GasCost::from(50_000)).unwrap();
+        gas.record_refund(4800);
+        assert_eq!(gas.final_refund(SpecId::London), GasCost::from(4800));
+        gas.record_refund(20_000);
+        assert_eq!(gas.final_refund(SpecId::London), GasCost::from(10_000));
+        assert_eq!(gas.final_refund(SpecId::Berlin), GasCost::from(24_800));
+        gas.record_refund(-30_000);
+        assert_eq!(gas.refunded(), -5200);
+        assert_eq!(gas.final_refund(SpecId::London), GasCost::ZERO);
+    }
+
+    #[test]
+    fn test_charge() {
+        let mut gas = Gas::new(100);
+        gas.charge([
+            (GasCategory::Base, GasCost::from(3)),
+            (GasCategory::Memory, GasCost::from(6)),
+        ])
+        .unwrap();
+        // nothing is charged unless the sum is enough
+        assert_eq!(
+            gas.charge([
+                (GasCategory::Base, GasCost::from(3)),
+                (GasCategory::ColdAccess, GasCost::from(90)),
+            ]),
+            Err(EvmError::out_of_gas(93, 91))
+        );
+        // forwarded gas is charged by the callee
+        gas.forward(GasCost::from(50)).unwrap();
+        gas.record_refund(10);
+        gas.consume_all();
+        assert_eq!(gas.left(), GasCost::ZERO);
+
+        let charges = gas.take_charges();
+        assert_eq!(charges.get(GasCategory::Base), GasCost::from(3));
+        assert_eq!(charges.get(GasCategory::ColdAccess), GasCost::ZERO);
+        assert_eq!(charges.get(GasCategory::Exception), GasCost::from(41));
+        assert_eq!(charges.total(), GasCost::from(50));
+        assert_eq!(charges.refunded(), 10);
+        assert_eq!(
+            charges.iter().collect::<Vec<_>>(),
+            [
+                (GasCategory::Base, GasCost::from(3)),
+                (GasCategory::Memory, GasCost::from(6)),
+                (GasCategory::Exception, GasCost::from(41)),
+            ]
+        );
+        assert!(gas.take_charges().is_empty());
+
+        // a caller takes over the charges of its callee, without the refunds if it failed
+        let mut caller = Gas::new(100);
+        caller.absorb(charges.without_refunds());
+        assert_eq!(caller.refunded(), 0);
+        caller.absorb(charges);
+        assert_eq!(caller.refunded(), 10);
+        assert_eq!(caller.take_charges().total(), GasCost::from(100));
+    }
+
+    #[test]
+    fn test_gas_report() {
+        let mut report = GasReport::default();
+        let mut gas = Gas::new(100);
+        gas.charge([(GasCategory::Intrinsic, GasCost::from(21))])
+            .unwrap();
+        report.record(None, gas.take_charges());
+        for pc in [0, 2, 0] {
+            gas.use_gas(GasCost::FASTEST).unwrap();
+            report.record(Some(pc), gas.take_charges());
+        }
+        report.record(Some(4), gas.take_charges());
+
+        assert_eq!(
+            report.total().get(GasCategory::Intrinsic),
+            GasCost::from(21)
+        );
+        assert_eq!(report.total().total(), gas.used());
+        assert_eq!(report.at_pc(0).get(GasCategory::Base), GasCost::from(6));
+        assert_eq!(report.at_pc(1), GasBreakdown::default());
+        // empty charges leave no entry
+        assert_eq!(report.by_pc().map(|(pc, _)| pc).collect::<Vec<_>>(), [0, 2]);
+    }
 }
@@ -0,0 +1,13 @@
+use crate::evm::Address;
+use primitive_types::U256;
+
+/// A log emitted by `LOG0` - `LOG4`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Log {
+    /// Address of the account that emitted the log
+    pub address: Address,
+    /// Topics of the log, at most 4
+    pub topics: Vec<U256>,
+    /// Data of the log
+    pub data: Vec<u8>,
+}
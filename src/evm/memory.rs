@@ -1,4 +1,5 @@
-use crate::evm::gas::GasCost;
+use crate::evm::gas::{GasCategory, GasCost};
+use crate::evm::utils::{copy_padded, keccak256};
 use crate::evm::{EvmError, Gas, OpcodeId, Stack};
 use primitive_types::U256;
 
@@ -33,39 +34,63 @@ impl Memory {
         self.word_size
     }
 
-    /// Calculate the memory gas cost of the given byte size
+    /// Calculate the memory gas cost of the given word size,
+    /// `3 * words + words² / 512`
     pub fn gas_cost(word_size: usize) -> GasCost {
-        ((word_size * word_size) as u64 * GasCost::MEMORY_EXPANSION_QUAD_DENOMINATOR.as_u64()
-            + word_size as u64 * GasCost::MEMORY_EXPANSION_LINEAR_COEFF.as_u64())
+        let word_size = word_size as u64;
+        (word_size * GasCost::MEMORY_EXPANSION_LINEAR_COEFF.as_u64()
+            + word_size * word_size / GasCost::MEMORY_EXPANSION_QUAD_DENOMINATOR.as_u64())
         .into()
     }
 
-    /// try expand the memory to the given size
-    /// could cause `OutOfGas` error
+    /// Convert a size popped from the stack, which can be at most `MAX_MEMORY_SIZE`
     ///
     /// # Returns
     ///
-    /// Returns the new word size if success, or `OutOfGas` error.
-    pub fn try_expand_to(
+    /// Returns the size as `usize`, or `MemoryLimitExceeded` error.
+    pub fn checked_size(size: U256) -> Result<usize, EvmError> {
+        if size > U256::from(MAX_MEMORY_SIZE) {
+            return Err(EvmError::MemoryLimitExceeded);
+        }
+        Ok(size.as_usize())
+    }
+
+    /// try expand the memory to cover `size` bytes from `offset`,
+    /// charging `charges` plus the expansion cost under `GasCategory::Memory`.
+    /// a zero-sized range never expands the memory, whatever its offset
+    ///
+    /// # Returns
+    ///
+    /// Returns the offset as `usize` if success, or zero for a zero-sized range.
+    /// When gas is not enough, returns `OutOfGas` error.
+    /// When the range ends past `MAX_MEMORY_SIZE`, returns `MemoryLimitExceeded` error.
+    pub fn try_expand_range(
         &mut self,
         offset: U256,
-        base_cost: GasCost,
+        size: usize,
+        charges: &[(GasCategory, GasCost)],
         gas: &mut Gas,
     ) -> Result<usize, EvmError> {
-        let offset: usize = offset.try_into().map_err(|_| EvmError::OutOfGas)?;
-        if offset < self.word_size * 32 {
-            gas.use_gas(base_cost)?;
+        if size == 0 {
+            gas.charge(charges.iter().copied())?;
+            return Ok(0);
+        }
+        let end = offset
+            .checked_add(U256::from(size))
+            .filter(|end| *end <= U256::from(MAX_MEMORY_SIZE))
+            .ok_or(EvmError::MemoryLimitExceeded)?;
+        let new_word_size = end.as_usize().div_ceil(32);
+        if new_word_size <= self.word_size {
             // no need to expand
-            return Ok(self.word_size);
+            gas.charge(charges.iter().copied())?;
+            return Ok(offset.as_usize());
         }
-        let prev_gas_cost = Self::gas_cost(self.word_size);
-        let new_word_size = (offset + 31) / 32;
-        let memory_expansion_cost = Self::gas_cost(new_word_size) - prev_gas_cost;
-        gas.use_gas(base_cost + memory_expansion_cost)?;
-        assert!(new_word_size * 32 <= MAX_MEMORY_SIZE);
+        let memory_expansion_cost = Self::gas_cost(new_word_size) - Self::gas_cost(self.word_size);
+        let expansion = (GasCategory::Memory, memory_expansion_cost);
+        gas.charge(charges.iter().copied().chain([expansion]))?;
         self.inner.resize(new_word_size * 32, 0);
         self.word_size = new_word_size;
-        Ok(new_word_size)
+        Ok(offset.as_usize())
     }
 
     /// Get a word from given offset
@@ -83,6 +108,24 @@ impl Memory {
         U256::from_big_endian(&self.inner[offset..offset + 32])
     }
 
+    /// Get a slice of `size` bytes from given offset
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn raw_slice(&self, offset: usize, size: usize) -> &[u8] {
+        &self.inner[offset..offset + size]
+    }
+
+    /// Get a mutable slice of `size` bytes from given offset
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn raw_slice_mut(&mut self, offset: usize, size: usize) -> &mut [u8] {
+        &mut self.inner[offset..offset + size]
+    }
+
     /// Implementation of the MLOAD opcode
     ///
     /// # Stack Inputs
@@ -97,15 +140,16 @@ impl Memory {
     ///
     /// Returns the value of the at the offset, or zero if the slot is empty.
     /// When gas is not enough, returns `OutOfGas` error.
+    /// When the memory would grow past `MAX_MEMORY_SIZE`, returns `MemoryLimitExceeded` error.
     /// When the stack is empty, returns `StackUnderflow` error.
     pub fn mload(&mut self, gas: &mut Gas, stack: &mut Stack) -> Result<U256, EvmError> {
         let static_gas = OpcodeId::MLOAD.constant_gas_cost();
 
         // key: memory offset to be read.
         let offset = stack.try_pop()?;
-        self.try_expand_to(offset, static_gas, gas)?;
+        let offset = self.try_expand_range(offset, 32, &[(GasCategory::Base, static_gas)], gas)?;
 
-        let value = self.raw_get(offset.as_usize());
+        let value = self.raw_get(offset);
         stack.try_push(value).unwrap(); // impossible to fail, so unwrap
         Ok(value)
     }
@@ -120,6 +164,7 @@ impl Memory {
     ///
     /// Returns nothing.
     /// When gas is not enough, returns `OutOfGas` error.
+    /// When the memory would grow past `MAX_MEMORY_SIZE`, returns `MemoryLimitExceeded` error.
     /// When the stack has less than 2 elements, returns `StackUnderflow` error.
     pub fn mstore(&mut self, gas: &mut Gas, stack: &mut Stack) -> Result<(), EvmError> {
         let static_gas = OpcodeId::MSTORE.constant_gas_cost();
@@ -128,10 +173,9 @@ impl Memory {
         let offset = stack.try_pop()?;
         // value: value to be stored in the memory.
         let value = stack.try_pop()?;
-        self.try_expand_to(offset, static_gas, gas)?;
+        let offset = self.try_expand_range(offset, 32, &[(GasCategory::Base, static_gas)], gas)?;
 
-        let offset = offset.as_usize();
-        let value = value.to_big_endian(&mut self.inner[offset..offset + 32]);
+        value.to_big_endian(&mut self.inner[offset..offset + 32]);
         Ok(())
     }
 
@@ -145,6 +189,7 @@ impl Memory {
     ///
     /// Returns nothing.
     /// When gas is not enough, returns `OutOfGas` error.
+    /// When the memory would grow past `MAX_MEMORY_SIZE`, returns `MemoryLimitExceeded` error.
     /// When the stack has less than 2 elements, returns `StackUnderflow` error.
     pub fn mstore8(&mut self, gas: &mut Gas, stack: &mut Stack) -> Result<(), EvmError> {
         let static_gas = OpcodeId::MSTORE8.constant_gas_cost();
@@ -153,10 +198,222 @@ impl Memory {
         let offset = stack.try_pop()?;
         // value: value to be stored in the memory.
         let value = stack.try_pop()?;
-        self.try_expand_to(offset, static_gas, gas)?;
+        let offset = self.try_expand_range(offset, 1, &[(GasCategory::Base, static_gas)], gas)?;
 
-        let offset = offset.as_usize();
         self.inner[offset] = value.byte(0);
         Ok(())
     }
+
+    /// Copy `size` bytes of `source` from `offset` into the memory at `dest_offset`,
+    /// bytes past the end of `source` are copied as zero.
+    /// used by the copy opcodes, e.g. `CALLDATACOPY` and `CODECOPY`
+    ///
+    /// Charges `base_cost` plus `COPY` gas for every word and the memory expansion.
+    ///
+    /// # Returns
+    ///
+    /// Returns nothing.
+    /// When gas is not enough, returns `OutOfGas` error.
+    /// When the memory would grow past `MAX_MEMORY_SIZE`, returns `MemoryLimitExceeded` error.
+    pub fn copy_from(
+        &mut self,
+        source: &[u8],
+        dest_offset: U256,
+        offset: U256,
+        size: U256,
+        base_cost: GasCost,
+        gas: &mut Gas,
+    ) -> Result<(), EvmError> {
+        let size = Self::checked_size(size)?;
+        let word_cost = GasCost::COPY.per_word(size);
+        let charges = [
+            (GasCategory::Base, base_cost),
+            (GasCategory::Copy, word_cost),
+        ];
+        let dest_offset = self.try_expand_range(dest_offset, size, &charges, gas)?;
+        copy_padded(
+            &mut self.inner[dest_offset..dest_offset + size],
+            source,
+            offset,
+        );
+        Ok(())
+    }
+
+    /// Implementation of the MCOPY opcode (EIP-5656)
+    ///
+    /// # Stack Inputs
+    /// - `dest_offset`: byte offset in the memory where the result will be copied
+    /// - `offset`: byte offset in the memory to copy from
+    /// - `size`: byte size to copy
+    ///
+    /// # Returns
+    ///
+    /// Returns nothing, overlapping ranges are copied as if through a buffer.
+    /// When gas is not enough, returns `OutOfGas` error.
+    /// When the memory would grow past `MAX_MEMORY_SIZE`, returns `MemoryLimitExceeded` error.
+    /// When the stack has less than 3 elements, returns `StackUnderflow` error.
+    pub fn mcopy(&mut self, gas: &mut Gas, stack: &mut Stack) -> Result<(), EvmError> {
+        let static_gas = OpcodeId::MCOPY.constant_gas_cost();
+
+        // dest_offset: memory offset to be modified.
+        let dest_offset = stack.try_pop()?;
+        // offset: memory offset to be copied from.
+        let offset = stack.try_pop()?;
+        // size: number of bytes to be copied.
+        let size = stack.try_pop()?;
+
+        let size = Self::checked_size(size)?;
+        let word_cost = GasCost::COPY.per_word(size);
+        let charges = [
+            (GasCategory::Base, static_gas),
+            (GasCategory::Copy, word_cost),
+        ];
+        if size == 0 {
+            gas.charge(charges)?;
+            return Ok(());
+        }
+        // both ranges have the same size, so covering the higher one covers both
+        self.try_expand_range(dest_offset.max(offset), size, &charges, gas)?;
+
+        let dest_offset = dest_offset.as_usize();
+        let offset = offset.as_usize();
+        self.inner.copy_within(offset..offset + size, dest_offset);
+        Ok(())
+    }
+
+    /// Implementation of the SHA3 (KECCAK256) opcode
+    ///
+    /// # Stack Inputs
+    /// - `offset`: byte offset in the memory
+    /// - `size`: byte size to read in the memory
+    ///
+    /// # Stack Outputs
+    /// - `hash`: Keccak-256 hash of the given data in memory
+    ///
+    /// # Returns
+    ///
+    /// Returns the hash.
+    /// When gas is not enough, returns `OutOfGas` error.
+    /// When the memory would grow past `MAX_MEMORY_SIZE`, returns `MemoryLimitExceeded` error.
+    /// When the stack has less than 2 elements, returns `StackUnderflow` error.
+    pub fn sha3(&mut self, gas: &mut Gas, stack: &mut Stack) -> Result<U256, EvmError> {
+        let static_gas = OpcodeId::SHA3.constant_gas_cost();
+
+        // offset: memory offset to be hashed.
+        let offset = stack.try_pop()?;
+        // size: number of bytes to be hashed.
+        let size = stack.try_pop()?;
+
+        let size = Self::checked_size(size)?;
+        let word_cost = GasCost::COPY_SHA3.per_word(size);
+        let charges = [
+            (GasCategory::Base, static_gas),
+            (GasCategory::Copy, word_cost),
+        ];
+        let offset = self.try_expand_range(offset, size, &charges, gas)?;
+
+        let hash = U256::from_big_endian(&keccak256(self.raw_slice(offset, size)));
+        stack.try_push(hash).unwrap(); // impossible to fail, so unwrap
+        Ok(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gas_cost() {
+        // words, cost
+        for (word_size, cost) in [
+            (0, 0),
+            (1, 3),
+            (32, 98),
+            (1024, 5120),
+            (MAX_MEMORY_SIZE / 32, 573440),
+        ] {
+            assert_eq!(Memory::gas_cost(word_size), GasCost::from(cost));
+        }
+    }
+
+    #[test]
+    fn test_expand_range() {
+        let mut memory = Memory::new();
+        let mut gas = Gas::new(1_000_000);
+
+        // a zero-sized range expands nothing, wherever it is
+        let offset = memory
+            .try_expand_range(
+                U256::MAX,
+                0,
+                &[(GasCategory::Base, GasCost::FASTEST)],
+                &mut gas,
+            )
+            .unwrap();
+        assert_eq!(offset, 0);
+        assert_eq!(memory.word_size(), 0);
+        assert_eq!(gas.used(), GasCost::FASTEST);
+
+        // a single byte at 32 needs the second word
+        memory
+            .try_expand_range(U256::from(32), 1, &[], &mut gas)
+            .unwrap();
+        assert_eq!(memory.word_size(), 2);
+        assert_eq!(gas.used(), GasCost::FASTEST + GasCost::from(6));
+
+        // only the expansion is charged
+        memory
+            .try_expand_range(U256::zero(), 1024 * 32, &[], &mut gas)
+            .unwrap();
+        assert_eq!(memory.word_size(), 1024);
+        assert_eq!(gas.used(), GasCost::FASTEST + GasCost::from(5120));
+        let charges = gas.take_charges();
+        assert_eq!(charges.get(GasCategory::Base), GasCost::FASTEST);
+        assert_eq!(charges.get(GasCategory::Memory), GasCost::from(5120));
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let mut memory = Memory::new();
+        let mut gas = Gas::new(u64::MAX);
+        memory
+            .try_expand_range(U256::zero(), MAX_MEMORY_SIZE, &[], &mut gas)
+            .unwrap();
+        assert_eq!(gas.used(), GasCost::from(573440));
+        for (offset, size) in [(U256::from(MAX_MEMORY_SIZE), 1), (U256::MAX, 32)] {
+            assert_eq!(
+                memory.try_expand_range(offset, size, &[], &mut gas),
+                Err(EvmError::MemoryLimitExceeded)
+            );
+        }
+        assert_eq!(
+            Memory::checked_size(U256::from(MAX_MEMORY_SIZE + 1)),
+            Err(EvmError::MemoryLimitExceeded)
+        );
+        assert_eq!(memory.word_size(), MAX_MEMORY_SIZE / 32);
+    }
+
+    #[test]
+    fn test_mload_mstore() {
+        let mut memory = Memory::new();
+        let mut gas = Gas::new(1_000_000);
+        let mut stack = Stack::default();
+
+        // a fresh offset expands the memory to cover the whole word
+        stack.try_push(U256::from(0x2a)).unwrap();
+        stack.try_push(U256::from(64)).unwrap();
+        memory.mstore(&mut gas, &mut stack).unwrap();
+        assert_eq!(memory.word_size(), 3);
+        assert_eq!(gas.used(), GasCost::from(3 + 9));
+
+        stack.try_push(U256::from(64)).unwrap();
+        assert_eq!(memory.mload(&mut gas, &mut stack), Ok(U256::from(0x2a)));
+        assert_eq!(gas.used(), GasCost::from(3 + 9 + 3));
+
+        stack.try_push(U256::from(0xff)).unwrap();
+        stack.try_push(U256::from(96)).unwrap();
+        memory.mstore8(&mut gas, &mut stack).unwrap();
+        assert_eq!(memory.word_size(), 4);
+        assert_eq!(memory.raw_slice(96, 1), &[0xff]);
+    }
 }
@@ -1,21 +1,45 @@
 mod access_list;
+mod block;
 mod bytecode;
+#[cfg(test)]
+mod conformance;
+mod env;
+mod error;
+mod fee;
+mod frame;
 mod gas;
+mod log;
 mod memory;
 mod opcodes;
+mod outcome;
+mod precompiles;
+mod spec;
 mod stack;
 mod storage;
+mod transaction;
 mod utils;
+mod world;
 
-use crate::evm::utils::SignExt;
-pub use access_list::AccessList;
+use crate::evm::utils::{address_to_word, copy_padded, SignExt};
+pub use access_list::{AccessList, AccessListItem};
+pub use block::BlockExecutor;
 pub use bytecode::{Bytecode, Instruction};
-pub use gas::{Gas, GasCost};
-pub use memory::Memory;
+pub use env::{Address, BlockEnv, Env, TxEnv};
+pub use error::{EvmError, ExecutionError, InvalidTransaction};
+pub use fee::{base_fee_schedule, format_units, next_base_fee, DynamicFee, Fee, ETHER, GWEI};
+pub use frame::{CallContext, CallFrame, MAX_CODE_SIZE, MAX_INITCODE_SIZE};
+pub use gas::{Gas, GasBreakdown, GasCategory, GasCost, GasReport};
+pub use log::Log;
+pub use memory::{Memory, MAX_MEMORY_SIZE};
 pub use opcodes::OpcodeId;
-use primitive_types::U256;
+pub use outcome::{ExecutionOutcome, HaltReason};
+pub use precompiles::{precompile, precompile_addresses, Precompile, PrecompileOutput};
+use primitive_types::{U256, U512};
+pub use spec::SpecId;
 pub use stack::Stack;
-pub use storage::Storage;
+pub use storage::{Storage, TransientStorage};
+pub use transaction::{execute_transaction, Receipt, Transaction};
+pub use world::{Account, WorldState};
 
 /// a simple emulator for the EVM
 pub struct Evm {
@@ -25,37 +49,73 @@ pub struct Evm {
     pub gas: Gas,
     pub memory: Memory,
     pub stack: Stack,
-    pub storage: Storage,
-}
-
-#[derive(Debug, Clone)]
-pub enum EvmError {
-    OutOfGas,
-    StackUnderflow,
-    StackOverflow,
+    /// Accounts with their balance, code and storage
+    pub world: WorldState,
+    /// Message context of the running frame
+    pub context: CallContext,
+    /// Suspended caller frames, the innermost last
+    pub frames: Vec<CallFrame>,
+    /// Data returned by `RETURN` or `REVERT`
+    pub return_data: Vec<u8>,
+    /// Data returned by the last call, read by `RETURNDATASIZE` and `RETURNDATACOPY`
+    pub return_data_buffer: Vec<u8>,
+    /// Set once the execution has halted
+    pub halt_reason: Option<HaltReason>,
+    /// Logs emitted by `LOG0` - `LOG4`
+    pub logs: Vec<Log>,
+    /// Input data of the execution
+    pub calldata: Vec<u8>,
+    /// Block and transaction context
+    pub env: Env,
+    /// Fork whose opcodes and gas rules apply
+    pub spec: SpecId,
+    /// Gas charged so far, per category and per instruction
+    pub gas_report: GasReport,
 }
 
 impl Evm {
-    pub fn new(gas_limit: impl Into<GasCost>) -> Self {
+    /// Creates an evm running the top level call of `env.tx`,
+    /// with the transaction's access list warm.
+    pub fn new(env: Env, gas_limit: impl Into<GasCost>) -> Self {
         Evm {
             program_counter: 0,
-            access_list: AccessList::default(),
+            access_list: AccessList::for_transaction(
+                SpecId::default(),
+                env.tx.origin,
+                env.tx.address,
+                &env.tx.access_list,
+            ),
             bytecode: Bytecode::default(),
             gas: Gas::new(gas_limit),
             memory: Memory::default(),
             stack: Stack::default(),
-            storage: Storage::default(),
+            world: WorldState::default(),
+            context: CallContext {
+                address: env.tx.address,
+                caller: env.tx.caller,
+                value: env.tx.value,
+                is_static: false,
+            },
+            frames: Vec::new(),
+            return_data: Vec::new(),
+            return_data_buffer: Vec::new(),
+            halt_reason: None,
+            logs: Vec::new(),
+            calldata: Vec::new(),
+            env,
+            spec: SpecId::default(),
+            gas_report: GasReport::default(),
         }
     }
 
-    pub fn into_parts(self) -> (usize, Bytecode, Stack, Memory, Gas, Storage, AccessList) {
+    pub fn into_parts(self) -> (usize, Bytecode, Stack, Memory, Gas, WorldState, AccessList) {
         (
             self.program_counter,
             self.bytecode,
             self.stack,
             self.memory,
             self.gas,
-            self.storage,
+            self.world,
             self.access_list,
         )
     }
@@ -66,7 +126,7 @@ impl Evm {
         stack: Stack,
         memory: Memory,
         gas: Gas,
-        storage: Storage,
+        world: WorldState,
         access_list: AccessList,
     ) -> Self {
         Evm {
@@ -76,18 +136,210 @@ impl Evm {
             gas,
             memory,
             stack,
-            storage,
+            world,
+            context: CallContext::default(),
+            frames: Vec::new(),
+            return_data: Vec::new(),
+            return_data_buffer: Vec::new(),
+            halt_reason: None,
+            logs: Vec::new(),
+            calldata: Vec::new(),
+            env: Env::default(),
+            spec: SpecId::default(),
+            gas_report: GasReport::default(),
         }
     }
 
+    /// Sets the input data of the execution
+    pub fn with_calldata(mut self, calldata: impl Into<Vec<u8>>) -> Self {
+        self.calldata = calldata.into();
+        self
+    }
+
+    /// Sets the fork whose opcodes and gas rules apply, the latest by default,
+    /// and starts over from the access list of the transaction under its rules.
+    pub fn with_spec(mut self, spec: SpecId) -> Self {
+        self.spec = spec;
+        self.access_list = AccessList::for_transaction(
+            spec,
+            self.env.tx.origin,
+            self.env.tx.address,
+            &self.env.tx.access_list,
+        );
+        self
+    }
+
     pub fn push_instruction(&mut self, instruction: Instruction) -> Result<(), EvmError> {
         self.bytecode.push(instruction);
         Ok(())
     }
 
-    pub fn step(&mut self) -> Result<(), EvmError> {
-        let opcode = self.bytecode.get_opcode(self.program_counter).unwrap();
+    /// Moves the program counter to `dest`, which must be a `JUMPDEST`
+    fn jump(&mut self, dest: U256) -> Result<(), EvmError> {
+        let error = EvmError::InvalidJump { destination: dest };
+        let dest: usize = dest.try_into().map_err(|_| error.clone())?;
+        if !self.bytecode.is_jump_destination(dest) {
+            return Err(error);
+        }
+        self.program_counter = dest;
+        Ok(())
+    }
+
+    /// Runs until the execution halts or `max_steps` steps have been executed,
+    /// steps of called contracts included.
+    ///
+    /// An exceptional halt consumes all the gas left.
+    pub fn run(&mut self, max_steps: usize) -> ExecutionOutcome {
+        for _ in 0..max_steps {
+            if self.halt_reason.is_some() {
+                break;
+            }
+            self.step_or_halt();
+        }
+        self.outcome()
+    }
+
+    /// Runs until the execution halts, steps of called contracts included.
+    ///
+    /// Every instruction which does not halt uses gas, so the gas limit bounds the execution.
+    pub fn run_to_halt(&mut self) -> ExecutionOutcome {
+        while self.halt_reason.is_none() {
+            self.step_or_halt();
+        }
+        self.outcome()
+    }
+
+    /// Executes the instruction at the program counter, halting exceptionally on error
+    fn step_or_halt(&mut self) {
+        if let Err(e) = self.step() {
+            self.halt_with(e);
+        }
+    }
+
+    /// Halts exceptionally with `e`, consuming all the gas left
+    pub(crate) fn halt_with(&mut self, e: ExecutionError) {
+        self.gas.consume_all();
+        self.record_gas(Some(e.pc));
+        self.return_data.clear();
+        self.halt_reason = Some(HaltReason::Exception(e));
+    }
+
+    /// Returns the end state of the execution so far,
+    /// discarding the logs of a failed execution.
+    pub fn outcome(&mut self) -> ExecutionOutcome {
+        // e.g. the code deposit of top level init code
+        self.record_gas(Some(self.program_counter));
+        if matches!(
+            self.halt_reason,
+            Some(HaltReason::Revert | HaltReason::Exception(_))
+        ) {
+            // logs of a failed execution are discarded
+            self.logs.clear();
+        }
+        let halt_reason = self
+            .halt_reason
+            .clone()
+            .unwrap_or(HaltReason::StepLimitReached);
+        // refunds of a failed execution are discarded
+        let gas_refunded = match halt_reason {
+            HaltReason::Stop | HaltReason::Return => self.gas.final_refund(self.spec),
+            _ => GasCost::ZERO,
+        };
+        ExecutionOutcome {
+            halt_reason,
+            return_data: self.return_data.clone(),
+            gas_used: self.gas.used(),
+            gas_refunded,
+            gas_left: self.gas.left(),
+        }
+    }
+
+    /// Executes the instruction at the program counter.
+    ///
+    /// When a called contract halts, the execution returns to its caller;
+    /// errors are only returned by the top level frame.
+    /// Does nothing once the execution has halted.
+    pub fn step(&mut self) -> Result<(), ExecutionError> {
+        if self.halt_reason.is_some() {
+            return Ok(());
+        }
+        let pc = self.program_counter;
+        // callers have moved past their CALL or CREATE
+        let top_level_pc = self
+            .frames
+            .first()
+            .map_or(pc, |frame| frame.program_counter - 1);
+        let opcode = self
+            .bytecode
+            .get_byte(pc)
+            .map_or(OpcodeId::STOP, OpcodeId::from);
+        let result =
+            self.execute_instruction()
+                .map_err(|error| ExecutionError { pc, opcode, error });
+        if self.frames.is_empty() {
+            self.record_gas(Some(top_level_pc));
+            return result;
+        }
+        if let Err(e) = result {
+            self.gas.consume_all();
+            self.return_data.clear();
+            self.halt_reason = Some(HaltReason::Exception(e));
+        }
+        if let Some(halt_reason) = self.halt_reason.take() {
+            self.exit_frame(halt_reason);
+        }
+        // charges of called contracts reach the report once they returned to the top level
+        if self.frames.is_empty() {
+            self.record_gas(Some(top_level_pc));
+        }
+        Ok(())
+    }
+
+    /// Moves the charges of the running frame into the gas report,
+    /// put down to the top level instruction at `pc` if any
+    pub(crate) fn record_gas(&mut self, pc: Option<usize>) {
+        let charges = self.gas.take_charges();
+        self.gas_report.record(pc, charges);
+    }
+
+    /// Executes the instruction at the program counter in the running frame.
+    fn execute_instruction(&mut self) -> Result<(), EvmError> {
+        // running off the end of the code is an implicit STOP
+        let opcode = match self.bytecode.get_opcode(self.program_counter) {
+            Ok(opcode) => opcode,
+            Err(e) => {
+                // invalid opcodes consume all the gas left
+                self.gas.consume_all();
+                return Err(e);
+            }
+        };
+        if !opcode.is_enabled_in(self.spec) {
+            // opcodes from later forks are invalid
+            self.gas.consume_all();
+            return Err(EvmError::InvalidOpcode(opcode.as_u8()));
+        }
+        self.stack.try_at_least(opcode.stack_inputs())?;
         match opcode {
+            OpcodeId::STOP => {
+                self.halt_reason = Some(HaltReason::Stop);
+            }
+            OpcodeId::RETURN | OpcodeId::REVERT => {
+                let offset = self.stack.try_pop()?;
+                let size = self.stack.try_pop()?;
+                let size = Memory::checked_size(size)?;
+                let offset = self.memory.try_expand_range(
+                    offset,
+                    size,
+                    &[(GasCategory::Base, opcode.constant_gas_cost())],
+                    &mut self.gas,
+                )?;
+                self.return_data = self.memory.raw_slice(offset, size).to_vec();
+                self.halt_reason = Some(if opcode == OpcodeId::RETURN {
+                    HaltReason::Return
+                } else {
+                    HaltReason::Revert
+                });
+            }
             OpcodeId::ADD | OpcodeId::MUL | OpcodeId::SUB => {
                 self.gas.use_gas(opcode.constant_gas_cost())?;
                 let a = self.stack.try_pop()?;
@@ -122,21 +374,22 @@ impl Evm {
                 if b == U256::zero() {
                     self.stack.try_push(U256::zero())?;
                 } else {
+                    let min_neg = U256::one() << 255;
                     let a_sign = a.is_neg();
                     let b_sign = b.is_neg();
-                    let result_sign = a_sign ^ b_sign;
-                    let a_abs = if a_sign { a.neg() } else { a };
-                    let b_abs = if b_sign { b.neg() } else { b };
-                    let result_abs = match opcode {
+                    let a_abs = a.abs();
+                    let b_abs = b.abs();
+                    let result = match opcode {
+                        // the only overflow, -2^255 / -1 is -2^255
+                        OpcodeId::SDIV if a == min_neg && b == !U256::zero() => min_neg,
+                        // the quotient is negative if the signs differ
+                        OpcodeId::SDIV if a_sign ^ b_sign => (a_abs / b_abs).neg(),
                         OpcodeId::SDIV => a_abs / b_abs,
+                        // the remainder has the sign of the dividend
+                        OpcodeId::SMOD if a_sign => (a_abs % b_abs).neg(),
                         OpcodeId::SMOD => a_abs % b_abs,
                         _ => unreachable!(),
                     };
-                    let result = if result_sign {
-                        result_abs.neg()
-                    } else {
-                        result_abs
-                    };
                     self.stack.try_push(result)?;
                 }
                 self.program_counter += 1;
@@ -149,35 +402,53 @@ impl Evm {
                 if n == U256::zero() {
                     self.stack.try_push(U256::zero())?;
                 } else {
+                    // the intermediate result is not wrapped at 2^256
                     let tmp = if opcode == OpcodeId::ADDMOD {
-                        a.overflowing_add(b).0
+                        U512::from(a) + U512::from(b)
                     } else {
-                        a.overflowing_mul(b).0
+                        a.full_mul(b)
                     };
-                    self.stack.try_push(tmp % n)?;
+                    let result = tmp % U512::from(n);
+                    // impossible to fail, the result is less than n
+                    self.stack.try_push(U256::try_from(result).unwrap())?;
                 }
                 self.program_counter += 1;
             }
-            OpcodeId::EXP => unimplemented!(),
+            OpcodeId::EXP => {
+                let base = self.stack.try_pop()?;
+                let exponent = self.stack.try_pop()?;
+                let exponent_bytes = (exponent.bits() as u64).div_ceil(8);
+                let byte_cost = if self.spec.is_enabled_in(SpecId::SpuriousDragon) {
+                    GasCost::EXP_BYTE_TIMES
+                } else {
+                    GasCost::EXP_BYTE_TIMES_FRONTIER
+                };
+                let dynamic_gas = GasCost::from(byte_cost.as_u64() * exponent_bytes);
+                self.gas.charge([
+                    (GasCategory::Base, opcode.constant_gas_cost()),
+                    (GasCategory::Dynamic, dynamic_gas),
+                ])?;
+                self.stack.try_push(base.overflowing_pow(exponent).0)?;
+                self.program_counter += 1;
+            }
             OpcodeId::SIGNEXTEND => {
                 self.gas.use_gas(opcode.constant_gas_cost())?;
-                let least_significant_byte = self.stack.try_pop()?.low_u64() as usize;
-                if least_significant_byte < 32 {
-                    let number = self.stack.try_pop()?;
-                    let byte_position = 8 * (31 - least_significant_byte);
-                    let test_bit = U256::one() << byte_position;
-                    let extend = if number & test_bit == U256::zero() {
-                        U256::zero()
+                let byte_number = self.stack.try_pop()?;
+                let number = self.stack.try_pop()?;
+                let result = if byte_number < U256::from(31) {
+                    // the sign bit of the byte, counting from the least significant one
+                    let sign_bit = 8 * byte_number.as_usize() + 7;
+                    let mask = (U256::one() << (sign_bit + 1)) - 1;
+                    if number.bit(sign_bit) {
+                        number | !mask
                     } else {
-                        (!U256::zero()) << byte_position
-                    };
-                    let result = number | extend;
-                    self.stack.try_push(result)?;
+                        number & mask
+                    }
                 } else {
-                    // If the byte number is greater than 31, then push the number back without modification.
-                    let number = self.stack.try_pop()?;
-                    self.stack.try_push(number)?;
-                }
+                    // the sign bit of byte 31 is already the top bit
+                    number
+                };
+                self.stack.try_push(result)?;
                 self.program_counter += 1;
             }
             OpcodeId::LT | OpcodeId::GT | OpcodeId::SLT | OpcodeId::SGT | OpcodeId::EQ => {
@@ -199,6 +470,16 @@ impl Evm {
                 }
                 self.program_counter += 1;
             }
+            OpcodeId::ISZERO => {
+                self.gas.use_gas(opcode.constant_gas_cost())?;
+                let a = self.stack.try_pop()?;
+                if a.is_zero() {
+                    self.stack.try_push(U256::one())?;
+                } else {
+                    self.stack.try_push(U256::zero())?;
+                }
+                self.program_counter += 1;
+            }
             OpcodeId::AND | OpcodeId::OR | OpcodeId::XOR => {
                 self.gas.use_gas(opcode.constant_gas_cost())?;
                 let a = self.stack.try_pop()?;
@@ -223,19 +504,131 @@ impl Evm {
                 let n = self.stack.try_pop()?;
                 let a = self.stack.try_pop()?;
                 let result = if n < U256::from(32) {
-                    let byte_position = 8 * (31 - n.low_u64() as usize);
-                    let test_bit = U256::one() << byte_position;
-                    if a & test_bit == U256::zero() {
-                        U256::zero()
+                    // byte 0 is the most significant one
+                    U256::from(a.byte(31 - n.as_usize()))
+                } else {
+                    U256::zero()
+                };
+                self.stack.try_push(result)?;
+                self.program_counter += 1;
+            }
+            OpcodeId::CALLDATALOAD => {
+                self.gas.use_gas(opcode.constant_gas_cost())?;
+                let offset = self.stack.try_pop()?;
+                let mut bytes = [0u8; 32];
+                copy_padded(&mut bytes, &self.calldata, offset);
+                self.stack.try_push(U256::from_big_endian(&bytes))?;
+                self.program_counter += 1;
+            }
+            OpcodeId::CALLDATASIZE => {
+                self.gas.use_gas(opcode.constant_gas_cost())?;
+                self.stack.try_push(U256::from(self.calldata.len()))?;
+                self.program_counter += 1;
+            }
+            OpcodeId::CALLDATACOPY => {
+                let dest_offset = self.stack.try_pop()?;
+                let offset = self.stack.try_pop()?;
+                let size = self.stack.try_pop()?;
+                self.memory.copy_from(
+                    &self.calldata,
+                    dest_offset,
+                    offset,
+                    size,
+                    opcode.constant_gas_cost(),
+                    &mut self.gas,
+                )?;
+                self.program_counter += 1;
+            }
+            OpcodeId::CODESIZE => {
+                self.gas.use_gas(opcode.constant_gas_cost())?;
+                self.stack.try_push(U256::from(self.bytecode.inner.len()))?;
+                self.program_counter += 1;
+            }
+            OpcodeId::CODECOPY => {
+                let dest_offset = self.stack.try_pop()?;
+                let offset = self.stack.try_pop()?;
+                let size = self.stack.try_pop()?;
+                self.memory.copy_from(
+                    &self.bytecode.to_bytes(),
+                    dest_offset,
+                    offset,
+                    size,
+                    opcode.constant_gas_cost(),
+                    &mut self.gas,
+                )?;
+                self.program_counter += 1;
+            }
+            OpcodeId::ADDRESS
+            | OpcodeId::ORIGIN
+            | OpcodeId::CALLER
+            | OpcodeId::CALLVALUE
+            | OpcodeId::GASPRICE
+            | OpcodeId::COINBASE
+            | OpcodeId::TIMESTAMP
+            | OpcodeId::NUMBER
+            | OpcodeId::DIFFICULTY
+            | OpcodeId::GASLIMIT
+            | OpcodeId::CHAINID
+            | OpcodeId::BASEFEE => {
+                self.gas.use_gas(opcode.constant_gas_cost())?;
+                let value = match opcode {
+                    OpcodeId::ADDRESS => address_to_word(self.context.address),
+                    OpcodeId::ORIGIN => address_to_word(self.env.tx.origin),
+                    OpcodeId::CALLER => address_to_word(self.context.caller),
+                    OpcodeId::CALLVALUE => self.context.value,
+                    OpcodeId::GASPRICE => self.env.tx.gas_price,
+                    OpcodeId::COINBASE => address_to_word(self.env.block.coinbase),
+                    OpcodeId::TIMESTAMP => U256::from(self.env.block.timestamp),
+                    OpcodeId::NUMBER => U256::from(self.env.block.number),
+                    OpcodeId::DIFFICULTY => self.env.block.prevrandao,
+                    OpcodeId::GASLIMIT => U256::from(self.env.block.gas_limit),
+                    OpcodeId::CHAINID => U256::from(self.env.chain_id),
+                    OpcodeId::BASEFEE => self.env.block.base_fee,
+                    _ => unreachable!(),
+                };
+                self.stack.try_push(value)?;
+                self.program_counter += 1;
+            }
+            OpcodeId::SHL | OpcodeId::SHR => {
+                self.gas.use_gas(opcode.constant_gas_cost())?;
+                let shift = self.stack.try_pop()?;
+                let value = self.stack.try_pop()?;
+                let result = if shift >= U256::from(256) {
+                    U256::zero()
+                } else {
+                    match opcode {
+                        OpcodeId::SHL => value << shift.as_usize(),
+                        OpcodeId::SHR => value >> shift.as_usize(),
+                        _ => unreachable!(),
+                    }
+                };
+                self.stack.try_push(result)?;
+                self.program_counter += 1;
+            }
+            OpcodeId::SAR => {
+                self.gas.use_gas(opcode.constant_gas_cost())?;
+                let shift = self.stack.try_pop()?;
+                let value = self.stack.try_pop()?;
+                let result = if value.is_neg() {
+                    // shift the bitwise complement and complement back, which fills with ones
+                    if shift >= U256::from(256) {
+                        !U256::zero()
                     } else {
-                        U256::one()
+                        !(!value >> shift.as_usize())
                     }
-                } else {
+                } else if shift >= U256::from(256) {
                     U256::zero()
+                } else {
+                    value >> shift.as_usize()
                 };
                 self.stack.try_push(result)?;
                 self.program_counter += 1;
             }
+            OpcodeId::POP => {
+                self.gas.use_gas(opcode.constant_gas_cost())?;
+                self.stack.try_pop()?;
+                self.program_counter += 1;
+            }
             OpcodeId::MLOAD => {
                 self.memory.mload(&mut self.gas, &mut self.stack)?;
                 self.program_counter += 1;
@@ -248,6 +641,56 @@ impl Evm {
                 self.memory.mstore8(&mut self.gas, &mut self.stack)?;
                 self.program_counter += 1;
             }
+            OpcodeId::JUMP => {
+                self.gas.use_gas(opcode.constant_gas_cost())?;
+                let dest = self.stack.try_pop()?;
+                self.jump(dest)?;
+            }
+            OpcodeId::JUMPI => {
+                self.gas.use_gas(opcode.constant_gas_cost())?;
+                let dest = self.stack.try_pop()?;
+                let condition = self.stack.try_pop()?;
+                if condition.is_zero() {
+                    self.program_counter += 1;
+                } else {
+                    self.jump(dest)?;
+                }
+            }
+            OpcodeId::JUMPDEST => {
+                self.gas.use_gas(opcode.constant_gas_cost())?;
+                self.program_counter += 1;
+            }
+            _ if opcode.is_log() => {
+                if self.context.is_static {
+                    return Err(EvmError::WriteProtection);
+                }
+                let n_topics = opcode.as_usize() - OpcodeId::LOG0.as_usize();
+                let offset = self.stack.try_pop()?;
+                let size = self.stack.try_pop()?;
+                let mut topics = Vec::with_capacity(n_topics);
+                for _ in 0..n_topics {
+                    topics.push(self.stack.try_pop()?);
+                }
+                let size = Memory::checked_size(size)?;
+                let dynamic_gas = GasCost::from(
+                    GasCost::LOG_TOPIC.as_u64() * n_topics as u64
+                        + GasCost::LOG_DATA_BYTE.as_u64() * size as u64,
+                );
+                let charges = [
+                    (GasCategory::Base, opcode.constant_gas_cost() + GasCost::LOG),
+                    (GasCategory::Dynamic, dynamic_gas),
+                ];
+                let offset = self
+                    .memory
+                    .try_expand_range(offset, size, &charges, &mut self.gas)?;
+                let data = self.memory.raw_slice(offset, size).to_vec();
+                self.logs.push(Log {
+                    address: self.context.address,
+                    topics,
+                    data,
+                });
+                self.program_counter += 1;
+            }
             OpcodeId::PC => {
                 self.gas.use_gas(opcode.constant_gas_cost())?;
                 self.stack.try_push(U256::from(self.program_counter))?;
@@ -267,15 +710,13 @@ impl Evm {
             _ if opcode.is_push() => {
                 self.gas.use_gas(opcode.constant_gas_cost())?;
                 let n_bytes = opcode.as_usize() - OpcodeId::PUSH0.as_usize();
-                // left pad big endian bytes
+                // push data running past the end of the code is padded with zeros on the right
                 let mut bytes = [0u8; 32];
-                for (idx, value) in self.bytecode.inner
-                    [self.program_counter + 1..self.program_counter + 1 + n_bytes]
-                    .iter()
-                    .rev()
-                    .enumerate()
-                {
-                    bytes[31 - idx] = value.value;
+                for (i, byte) in bytes[32 - n_bytes..].iter_mut().enumerate() {
+                    *byte = self
+                        .bytecode
+                        .get_byte(self.program_counter + 1 + i)
+                        .unwrap_or(0);
                 }
                 self.stack.try_push(U256::from_big_endian(&bytes))?;
                 self.program_counter += 1 + n_bytes;
@@ -292,17 +733,87 @@ impl Evm {
                     .try_swap(opcode.as_usize() - OpcodeId::SWAP1.as_usize())?;
                 self.program_counter += 1;
             }
-            OpcodeId::SHA3 => unimplemented!(),
+            OpcodeId::SHA3 => {
+                self.memory.sha3(&mut self.gas, &mut self.stack)?;
+                self.program_counter += 1;
+            }
             OpcodeId::SLOAD => {
-                self.storage
-                    .sload(&mut self.access_list, &mut self.gas, &mut self.stack)?;
+                self.world.sload(
+                    self.spec,
+                    self.context.address,
+                    &mut self.access_list,
+                    &mut self.gas,
+                    &mut self.stack,
+                )?;
                 self.program_counter += 1;
             }
             OpcodeId::SSTORE => {
-                self.storage
-                    .sstore(&mut self.access_list, &mut self.gas, &mut self.stack)?;
+                if self.context.is_static {
+                    return Err(EvmError::WriteProtection);
+                }
+                self.world.sstore(
+                    self.spec,
+                    self.context.address,
+                    &mut self.access_list,
+                    &mut self.gas,
+                    &mut self.stack,
+                )?;
+                self.program_counter += 1;
+            }
+            OpcodeId::TLOAD => {
+                self.world
+                    .tload(self.context.address, &mut self.gas, &mut self.stack)?;
                 self.program_counter += 1;
             }
+            OpcodeId::TSTORE => {
+                if self.context.is_static {
+                    return Err(EvmError::WriteProtection);
+                }
+                self.world
+                    .tstore(self.context.address, &mut self.gas, &mut self.stack)?;
+                self.program_counter += 1;
+            }
+            OpcodeId::MCOPY => {
+                self.memory.mcopy(&mut self.gas, &mut self.stack)?;
+                self.program_counter += 1;
+            }
+            OpcodeId::RETURNDATASIZE => {
+                self.gas.use_gas(opcode.constant_gas_cost())?;
+                self.stack
+                    .try_push(U256::from(self.return_data_buffer.len()))?;
+                self.program_counter += 1;
+            }
+            OpcodeId::RETURNDATACOPY => {
+                let dest_offset = self.stack.try_pop()?;
+                let offset = self.stack.try_pop()?;
+                let size = self.stack.try_pop()?;
+                // unlike the other copies, reading past the end is an error
+                let available = self.return_data_buffer.len();
+                let out_of_bounds = EvmError::ReturnDataOutOfBounds {
+                    offset,
+                    size,
+                    available,
+                };
+                let end = offset.checked_add(size).ok_or(out_of_bounds.clone())?;
+                if end > U256::from(available) {
+                    return Err(out_of_bounds);
+                }
+                self.memory.copy_from(
+                    &self.return_data_buffer,
+                    dest_offset,
+                    offset,
+                    size,
+                    opcode.constant_gas_cost(),
+                    &mut self.gas,
+                )?;
+                self.program_counter += 1;
+            }
+            OpcodeId::CALL | OpcodeId::CALLCODE | OpcodeId::DELEGATECALL | OpcodeId::STATICCALL => {
+                self.call(opcode)?;
+            }
+            OpcodeId::CREATE | OpcodeId::CREATE2 => {
+                self.create(opcode)?;
+            }
             OpcodeId::GAS => {
                 self.gas.use_gas(opcode.constant_gas_cost())?;
                 self.stack.try_push(U256::from(self.gas.left().as_u64()))?;
@@ -317,19 +828,20 @@ impl Evm {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use smallvec::SmallVec;
+    use std::str::FromStr;
+    use strum::IntoEnumIterator;
 
     #[test]
     fn test_evm() {
-        let mut evm = Evm::new(1000);
+        let mut evm = Evm::new(Env::default(), 1000);
         evm.push_instruction(Instruction {
             opcode: OpcodeId::PUSH1,
-            push_data: Some(SmallVec::from(vec![0x01])),
+            push_data: Some([0x01; 32]),
         })
         .unwrap();
         evm.push_instruction(Instruction {
             opcode: OpcodeId::PUSH1,
-            push_data: Some(SmallVec::from(vec![0x02])),
+            push_data: Some([0x02; 32]),
         })
         .unwrap();
         evm.push_instruction(Instruction {
@@ -342,4 +854,824 @@ mod tests {
         evm.step().unwrap();
         assert_eq!(evm.stack.try_pop().unwrap(), U256::from(3));
     }
+
+    /// Executes a single `opcode` with `inputs` on the stack (first item on top)
+    /// and returns the resulting evm.
+    fn exec_single(opcode: OpcodeId, inputs: &[U256]) -> Result<Evm, EvmError> {
+        let mut evm = Evm::new(Env::default(), 100000);
+        for input in inputs.iter().rev() {
+            evm.stack.try_push(*input).unwrap();
+        }
+        evm.push_instruction(Instruction {
+            opcode,
+            push_data: None,
+        })
+        .unwrap();
+        evm.step().map_err(|e| e.error)?;
+        Ok(evm)
+    }
+
+    fn u256(s: &str) -> U256 {
+        U256::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_pop() {
+        let evm = exec_single(OpcodeId::POP, &[U256::one(), U256::from(2)]).unwrap();
+        assert_eq!(evm.stack.inner, vec![U256::from(2)]);
+        assert_eq!(
+            exec_single(OpcodeId::POP, &[]).err(),
+            Some(EvmError::StackUnderflow {
+                required: 1,
+                available: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_shifts() {
+        let max = !U256::zero();
+        let min_neg = U256::one() << 255;
+        let cases = [
+            // (opcode, shift, value, expected)
+            (OpcodeId::SHL, 0, U256::one(), U256::one()),
+            (OpcodeId::SHL, 1, U256::one(), U256::from(2)),
+            (OpcodeId::SHL, 255, U256::one(), min_neg),
+            (OpcodeId::SHL, 256, U256::one(), U256::zero()),
+            (OpcodeId::SHL, 1, max, max - 1),
+            (OpcodeId::SHR, 1, U256::one(), U256::zero()),
+            (OpcodeId::SHR, 1, min_neg, U256::one() << 254),
+            (OpcodeId::SHR, 255, max, U256::one()),
+            (OpcodeId::SHR, 256, max, U256::zero()),
+            (OpcodeId::SAR, 1, U256::one(), U256::zero()),
+            (
+                OpcodeId::SAR,
+                1,
+                min_neg,
+                u256("0xc000000000000000000000000000000000000000000000000000000000000000"),
+            ),
+            (OpcodeId::SAR, 255, min_neg, max),
+            (OpcodeId::SAR, 256, min_neg, max),
+            (OpcodeId::SAR, 0, max, max),
+            (OpcodeId::SAR, 1, max, max),
+            (
+                OpcodeId::SAR,
+                254,
+                u256("0x4000000000000000000000000000000000000000000000000000000000000000"),
+                U256::one(),
+            ),
+            (OpcodeId::SAR, 256, max >> 1, U256::zero()),
+        ];
+        for (opcode, shift, value, expected) in cases {
+            let evm = exec_single(opcode, &[U256::from(shift), value]).unwrap();
+            assert_eq!(
+                evm.stack.inner,
+                vec![expected],
+                "{:?} {} {}",
+                opcode,
+                shift,
+                value
+            );
+        }
+        // shift amounts that do not fit in a word must not panic
+        let evm = exec_single(OpcodeId::SAR, &[max, min_neg]).unwrap();
+        assert_eq!(evm.stack.inner, vec![max]);
+    }
+
+    #[test]
+    fn test_step_every_opcode() {
+        for operand in [U256::zero(), U256::one(), !U256::zero()] {
+            for opcode in OpcodeId::iter() {
+                let mut evm = Evm::new(Env::default(), 1_000_000);
+                // give memory opcodes something to read
+                evm.memory
+                    .try_expand_range(U256::zero(), 64, &[], &mut evm.gas)
+                    .unwrap();
+                // SWAP16 needs the most stack items
+                for _ in 0..17 {
+                    evm.stack.try_push(operand).unwrap();
+                }
+                let push_data = if opcode.is_push() && opcode != OpcodeId::PUSH0 {
+                    Some([0xff; 32])
+                } else {
+                    None
+                };
+                evm.push_instruction(Instruction { opcode, push_data })
+                    .unwrap();
+                let _ = evm.step();
+            }
+        }
+    }
+
+    #[test]
+    fn test_exp() {
+        let max = !U256::zero();
+        let cases = [
+            // (base, exponent, expected, gas)
+            (U256::from(2), U256::zero(), U256::one(), 10),
+            (U256::zero(), U256::zero(), U256::one(), 10),
+            (U256::zero(), U256::from(5), U256::zero(), 60),
+            (U256::from(2), U256::from(10), U256::from(1024), 60),
+            (
+                U256::from(3),
+                U256::from(100),
+                U256::from_dec_str("515377520732011331036461129765621272702107522001").unwrap(),
+                60,
+            ),
+            (U256::from(2), U256::from(256), U256::zero(), 110),
+            (U256::from(2), max, U256::zero(), 1610),
+            (max, max, max, 1610),
+            (max, max - 1, U256::one(), 1610),
+        ];
+        for (base, exponent, expected, gas) in cases {
+            let evm = exec_single(OpcodeId::EXP, &[base, exponent]).unwrap();
+            assert_eq!(evm.stack.inner, vec![expected], "{} ** {}", base, exponent);
+            assert_eq!(evm.gas.used().as_u64(), gas, "{} ** {}", base, exponent);
+        }
+    }
+
+    #[test]
+    fn test_sha3() {
+        // empty input touches no memory
+        let evm = exec_single(OpcodeId::SHA3, &[U256::from(1000), U256::zero()]).unwrap();
+        assert_eq!(
+            evm.stack.inner,
+            vec![u256(
+                "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+            )]
+        );
+        assert_eq!(evm.memory.word_size(), 0);
+        assert_eq!(evm.gas.used(), GasCost::SHA3);
+
+        // keccak256 of one zero word, paying for one copied word and one word of memory
+        let evm = exec_single(OpcodeId::SHA3, &[U256::zero(), U256::from(32)]).unwrap();
+        assert_eq!(
+            evm.stack.inner,
+            vec![u256(
+                "0x290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563"
+            )]
+        );
+        assert_eq!(evm.memory.word_size(), 1);
+        assert_eq!(
+            evm.gas.used(),
+            GasCost::SHA3 + GasCost::COPY_SHA3 + Memory::gas_cost(1)
+        );
+    }
+
+    #[test]
+    fn test_jump() {
+        let mut evm = Evm::new(Env::default(), 1000);
+        for (opcode, value) in [
+            // skip over the invalid jump below
+            (OpcodeId::PUSH1, Some(6)),
+            (OpcodeId::JUMP, None),
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::JUMP, None),
+            (OpcodeId::JUMPDEST, None),
+            (OpcodeId::PUSH1, Some(0x2a)),
+        ] {
+            evm.push_instruction(Instruction {
+                opcode,
+                push_data: value.map(|value| [value; 32]),
+            })
+            .unwrap();
+        }
+        evm.step().unwrap();
+        evm.step().unwrap();
+        assert_eq!(evm.program_counter, 6);
+        evm.step().unwrap();
+        evm.step().unwrap();
+        assert_eq!(evm.stack.inner, vec![U256::from(0x2a)]);
+        assert_eq!(
+            evm.gas.used(),
+            GasCost::FASTEST + GasCost::MID + GasCost::ONE + GasCost::FASTEST
+        );
+    }
+
+    #[test]
+    fn test_jumpi() {
+        let mut evm = Evm::new(Env::default(), 1000);
+        for (opcode, value) in [
+            (OpcodeId::PUSH1, Some(0)),
+            (OpcodeId::PUSH1, Some(10)),
+            // not taken
+            (OpcodeId::JUMPI, None),
+            (OpcodeId::PUSH1, Some(1)),
+            (OpcodeId::PUSH1, Some(10)),
+            // taken
+            (OpcodeId::JUMPI, None),
+            (OpcodeId::JUMPDEST, None),
+        ] {
+            evm.push_instruction(Instruction {
+                opcode,
+                push_data: value.map(|value| [value; 32]),
+            })
+            .unwrap();
+        }
+        evm.step().unwrap();
+        evm.step().unwrap();
+        evm.step().unwrap();
+        assert_eq!(evm.program_counter, 5);
+        evm.step().unwrap();
+        evm.step().unwrap();
+        evm.step().unwrap();
+        assert_eq!(evm.program_counter, 10);
+        assert!(evm.stack.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_jump() {
+        // the JUMPDEST byte at 1 is push data, 3 is the JUMP itself, 100 is out of bounds
+        for dest in [1, 3, 100] {
+            let mut evm = Evm::new(Env::default(), 1000);
+            let mut push_data = [0u8; 32];
+            push_data[0] = OpcodeId::JUMPDEST.as_u8();
+            evm.push_instruction(Instruction {
+                opcode: OpcodeId::PUSH1,
+                push_data: Some(push_data),
+            })
+            .unwrap();
+            evm.push_instruction(Instruction {
+                opcode: OpcodeId::PUSH1,
+                push_data: Some([dest; 32]),
+            })
+            .unwrap();
+            evm.push_instruction(Instruction {
+                opcode: OpcodeId::JUMP,
+                push_data: None,
+            })
+            .unwrap();
+            evm.step().unwrap();
+            evm.step().unwrap();
+            assert_eq!(
+                evm.step().map_err(|e| e.error),
+                Err(EvmError::InvalidJump {
+                    destination: U256::from(dest)
+                })
+            );
+        }
+        let result = exec_single(OpcodeId::JUMP, &[U256::MAX]);
+        assert_eq!(
+            result.err(),
+            Some(EvmError::InvalidJump {
+                destination: U256::MAX
+            })
+        );
+    }
+
+    /// Builds an evm running `instructions`, with push data given as a number.
+    fn evm_with_code(gas_limit: u64, instructions: &[(OpcodeId, u8)]) -> Evm {
+        let mut evm = Evm::new(Env::default(), gas_limit);
+        for &(opcode, value) in instructions {
+            let push_data = if opcode.is_push() && opcode != OpcodeId::PUSH0 {
+                let mut push_data = [0u8; 32];
+                let n_bytes = opcode.as_usize() - OpcodeId::PUSH0.as_usize();
+                push_data[n_bytes - 1] = value;
+                Some(push_data)
+            } else {
+                None
+            };
+            evm.push_instruction(Instruction { opcode, push_data })
+                .unwrap();
+        }
+        evm
+    }
+
+    #[test]
+    fn test_run_off_the_end() {
+        let mut evm = evm_with_code(1000, &[(OpcodeId::PUSH1, 1)]);
+        let outcome = evm.run(10);
+        assert_eq!(outcome.halt_reason, HaltReason::Stop);
+        assert!(outcome.is_success());
+        assert_eq!(outcome.gas_used, GasCost::FASTEST);
+        assert_eq!(outcome.gas_left, GasCost::from(997));
+        assert_eq!(evm.stack.inner, vec![U256::one()]);
+
+        // empty code stops immediately
+        let outcome = Evm::new(Env::default(), 1000).run(10);
+        assert_eq!(outcome.halt_reason, HaltReason::Stop);
+        assert_eq!(outcome.gas_used, GasCost::ZERO);
+    }
+
+    #[test]
+    fn test_truncated_push() {
+        // push data running past the end of the code is padded with zeros on the right
+        for (code, value) in [
+            (vec![0x61, 0x01], U256::from(0x0100)),
+            (vec![0x7f, 0xab, 0xcd], U256::from(0xabcd) << 240),
+            (vec![0x7f], U256::zero()),
+        ] {
+            let mut evm = Evm::new(Env::default(), 1000);
+            evm.bytecode = Bytecode::from(&code[..]);
+            evm.step().unwrap();
+            assert_eq!(evm.stack.inner, vec![value]);
+            assert_eq!(evm.run(10).halt_reason, HaltReason::Stop);
+        }
+    }
+
+    #[test]
+    fn test_stop() {
+        let mut evm = evm_with_code(
+            1000,
+            &[
+                (OpcodeId::PUSH1, 1),
+                (OpcodeId::STOP, 0),
+                (OpcodeId::PUSH1, 2),
+            ],
+        );
+        let outcome = evm.run(10);
+        assert_eq!(outcome.halt_reason, HaltReason::Stop);
+        assert!(outcome.return_data.is_empty());
+        assert_eq!(evm.stack.inner, vec![U256::one()]);
+        // stepping a halted evm does nothing
+        evm.step().unwrap();
+        assert_eq!(evm.stack.inner, vec![U256::one()]);
+    }
+
+    #[test]
+    fn test_return_and_revert() {
+        for (opcode, halt_reason) in [
+            (OpcodeId::RETURN, HaltReason::Return),
+            (OpcodeId::REVERT, HaltReason::Revert),
+        ] {
+            let mut evm = evm_with_code(
+                1000,
+                &[
+                    (OpcodeId::PUSH1, 0x2a),
+                    (OpcodeId::PUSH1, 1),
+                    (OpcodeId::MSTORE8, 0),
+                    (OpcodeId::PUSH1, 2),
+                    (OpcodeId::PUSH1, 0),
+                    (opcode, 0),
+                ],
+            );
+            let outcome = evm.run(10);
+            assert_eq!(outcome.halt_reason, halt_reason);
+            assert_eq!(outcome.is_success(), opcode == OpcodeId::RETURN);
+            assert_eq!(outcome.is_revert(), opcode == OpcodeId::REVERT);
+            assert_eq!(outcome.return_data, vec![0, 0x2a]);
+            assert_eq!(outcome.gas_used + outcome.gas_left, GasCost::from(1000));
+        }
+    }
+
+    #[test]
+    fn test_run_exception_and_step_limit() {
+        let mut evm = evm_with_code(1000, &[(OpcodeId::PUSH1, 1), (OpcodeId::ADD, 0)]);
+        let outcome = evm.run(10);
+        let HaltReason::Exception(error) = outcome.halt_reason else {
+            panic!("expected an exceptional halt");
+        };
+        assert_eq!(
+            error.to_string(),
+            "ADD needed 2 stack items, had 1 at pc 0x02"
+        );
+        assert_eq!(outcome.gas_used, GasCost::from(1000));
+        assert_eq!(outcome.gas_left, GasCost::ZERO);
+
+        let mut evm = evm_with_code(1000, &[(OpcodeId::PUSH1, 1), (OpcodeId::PUSH1, 2)]);
+        let outcome = evm.run(1);
+        assert_eq!(outcome.halt_reason, HaltReason::StepLimitReached);
+        assert!(!outcome.is_success());
+        assert_eq!(evm.program_counter, 2);
+    }
+
+    #[test]
+    fn test_invalid_opcode() {
+        for value in [0xfe, 0x0c, 0xef] {
+            let mut evm = Evm::new(Env::default(), 1000);
+            evm.bytecode = Bytecode::from(&[0x60, 0x01, value][..]);
+            evm.step().unwrap();
+            assert_eq!(
+                evm.step(),
+                Err(ExecutionError {
+                    pc: 2,
+                    opcode: OpcodeId::from(value),
+                    error: EvmError::InvalidOpcode(value)
+                })
+            );
+            assert_eq!(evm.gas.left(), GasCost::ZERO);
+        }
+
+        let mut evm = Evm::new(Env::default(), 1000);
+        evm.bytecode = Bytecode::from(&[0x60, 0x01, 0xfe][..]);
+        let outcome = evm.run(10);
+        assert_eq!(
+            outcome.halt_reason.error(),
+            Some(&EvmError::InvalidOpcode(0xfe))
+        );
+        assert_eq!(outcome.gas_used, GasCost::from(1000));
+    }
+
+    #[test]
+    fn test_spec_opcodes() {
+        // PUSH0 only exists from Shanghai
+        for (spec, result) in [
+            (SpecId::London, Err(EvmError::InvalidOpcode(0x5f))),
+            (SpecId::Shanghai, Ok(())),
+        ] {
+            let mut evm = Evm::new(Env::default(), 1000).with_spec(spec);
+            evm.bytecode = Bytecode::from(&[0x5f][..]);
+            assert_eq!(evm.step().map_err(|e| e.error), result);
+        }
+        let mut evm = Evm::new(Env::default(), 1000).with_spec(SpecId::Byzantium);
+        evm.bytecode = Bytecode::from(&[0x60, 0x01, 0x60, 0x01, 0x1b][..]);
+        let outcome = evm.run(10);
+        assert_eq!(
+            outcome.halt_reason.error(),
+            Some(&EvmError::InvalidOpcode(0x1b))
+        );
+        assert_eq!(outcome.gas_used, GasCost::from(1000));
+    }
+
+    #[test]
+    fn test_spec_gas() {
+        let gas_used = |spec, instructions: &[(OpcodeId, u8)]| {
+            let mut evm = evm_with_code(100_000, instructions);
+            evm.spec = spec;
+            evm.run(10).gas_used.as_u64()
+        };
+        let sstore = [
+            (OpcodeId::PUSH1, 1),
+            (OpcodeId::PUSH1, 0),
+            (OpcodeId::SSTORE, 0),
+            (OpcodeId::PUSH1, 2),
+            (OpcodeId::PUSH1, 0),
+            (OpcodeId::SSTORE, 0),
+        ];
+        // set, then reset a dirty slot
+        assert_eq!(gas_used(SpecId::Petersburg, &sstore), 12 + 20000 + 5000);
+        assert_eq!(gas_used(SpecId::Istanbul, &sstore), 12 + 20000 + 800);
+        // the first access is cold
+        assert_eq!(gas_used(SpecId::London, &sstore), 12 + 22100 + 100);
+
+        let sload = [(OpcodeId::PUSH1, 0), (OpcodeId::SLOAD, 0)];
+        assert_eq!(gas_used(SpecId::Homestead, &sload), 3 + 50);
+        assert_eq!(gas_used(SpecId::Istanbul, &sload), 3 + 800);
+        assert_eq!(gas_used(SpecId::Berlin, &sload), 3 + 2100);
+
+        let exp = [
+            (OpcodeId::PUSH1, 2),
+            (OpcodeId::PUSH1, 2),
+            (OpcodeId::EXP, 0),
+        ];
+        assert_eq!(gas_used(SpecId::Frontier, &exp), 6 + 10 + 10);
+        assert_eq!(gas_used(SpecId::SpuriousDragon, &exp), 6 + 10 + 50);
+    }
+
+    #[test]
+    fn test_refund() {
+        // sets slot 0 and clears it again
+        let mut instructions = vec![
+            (OpcodeId::PUSH1, 1),
+            (OpcodeId::PUSH1, 0),
+            (OpcodeId::SSTORE, 0),
+            (OpcodeId::PUSH1, 0),
+            (OpcodeId::PUSH1, 0),
+            (OpcodeId::SSTORE, 0),
+        ];
+        let run = |spec, instructions: &[(OpcodeId, u8)]| {
+            let mut evm = evm_with_code(100_000, instructions);
+            evm.spec = spec;
+            evm.run(10)
+        };
+        let outcome = run(SpecId::London, &instructions);
+        assert_eq!(outcome.gas_used, GasCost::from(22212));
+        // 19900 is earned, capped at a fifth of the gas used
+        assert_eq!(outcome.gas_refunded, GasCost::from(22212 / 5));
+        assert_eq!(
+            outcome.gas_used_after_refund(),
+            GasCost::from(22212 - 22212 / 5)
+        );
+        // and at half before London
+        let outcome = run(SpecId::Berlin, &instructions);
+        assert_eq!(outcome.gas_refunded, GasCost::from(22212 / 2));
+
+        instructions.extend([
+            (OpcodeId::PUSH1, 0),
+            (OpcodeId::PUSH1, 0),
+            (OpcodeId::REVERT, 0),
+        ]);
+        let outcome = run(SpecId::London, &instructions);
+        assert!(outcome.is_revert());
+        assert_eq!(outcome.gas_refunded, GasCost::ZERO);
+        assert_eq!(outcome.gas_used_after_refund(), outcome.gas_used);
+    }
+
+    #[test]
+    fn test_log() {
+        let mut evm = evm_with_code(
+            10000,
+            &[
+                (OpcodeId::PUSH1, 0x2a),
+                (OpcodeId::PUSH1, 31),
+                (OpcodeId::MSTORE8, 0),
+                (OpcodeId::PUSH1, 0),
+                (OpcodeId::PUSH1, 0),
+                (OpcodeId::LOG0, 0),
+                (OpcodeId::PUSH1, 0xbb),
+                (OpcodeId::PUSH1, 0xaa),
+                (OpcodeId::PUSH1, 2),
+                (OpcodeId::PUSH1, 30),
+                (OpcodeId::LOG2, 0),
+            ],
+        );
+        for _ in 0..5 {
+            evm.step().unwrap();
+        }
+        let gas_used = evm.gas.used();
+        evm.step().unwrap();
+        assert_eq!(evm.gas.used() - gas_used, GasCost::LOG);
+        for _ in 0..4 {
+            evm.step().unwrap();
+        }
+        let gas_used = evm.gas.used();
+        evm.step().unwrap();
+        assert_eq!(
+            evm.gas.used() - gas_used,
+            GasCost::LOG + GasCost::LOG_TOPIC.as_u64() * 2 + GasCost::LOG_DATA_BYTE.as_u64() * 2
+        );
+        assert_eq!(
+            evm.logs,
+            vec![
+                Log::default(),
+                Log {
+                    address: Address::zero(),
+                    topics: vec![U256::from(0xaa), U256::from(0xbb)],
+                    data: vec![0, 0x2a],
+                },
+            ]
+        );
+        assert!(evm.stack.is_empty());
+    }
+
+    #[test]
+    fn test_log_discarded_on_revert() {
+        let mut evm = evm_with_code(
+            10000,
+            &[
+                (OpcodeId::PUSH1, 0),
+                (OpcodeId::PUSH1, 0),
+                (OpcodeId::LOG0, 0),
+                (OpcodeId::PUSH1, 0),
+                (OpcodeId::PUSH1, 0),
+                (OpcodeId::REVERT, 0),
+            ],
+        );
+        let outcome = evm.run(10);
+        assert!(outcome.is_revert());
+        assert!(evm.logs.is_empty());
+    }
+
+    #[test]
+    fn test_calldata() {
+        // read the two arguments from calldata and return their sum
+        let mut calldata = vec![0u8; 64];
+        calldata[31] = 40;
+        calldata[63] = 2;
+        let mut evm = evm_with_code(
+            1000,
+            &[
+                (OpcodeId::PUSH1, 0),
+                (OpcodeId::CALLDATALOAD, 0),
+                (OpcodeId::PUSH1, 32),
+                (OpcodeId::CALLDATALOAD, 0),
+                (OpcodeId::ADD, 0),
+                (OpcodeId::PUSH1, 31),
+                (OpcodeId::MSTORE8, 0),
+                (OpcodeId::PUSH1, 32),
+                (OpcodeId::PUSH1, 0),
+                (OpcodeId::RETURN, 0),
+            ],
+        )
+        .with_calldata(calldata);
+        let outcome = evm.run(20);
+        assert!(outcome.is_success());
+        assert_eq!(U256::from_big_endian(&outcome.return_data), U256::from(42));
+    }
+
+    #[test]
+    fn test_calldataload_padding() {
+        let mut evm = evm_with_code(
+            1000,
+            &[
+                (OpcodeId::CALLDATASIZE, 0),
+                (OpcodeId::PUSH1, 1),
+                (OpcodeId::CALLDATALOAD, 0),
+                (OpcodeId::PUSH1, 2),
+                (OpcodeId::CALLDATALOAD, 0),
+                (OpcodeId::PUSH1, 0xff),
+                (OpcodeId::CALLDATALOAD, 0),
+            ],
+        )
+        .with_calldata(vec![0xaa, 0xbb]);
+        evm.run(10);
+        assert_eq!(
+            evm.stack.inner,
+            vec![
+                U256::from(2),
+                U256::from(0xbb) << 248,
+                U256::zero(),
+                U256::zero(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_calldatacopy() {
+        let evm = Evm::new(Env::default(), 1000).with_calldata(vec![0xaa, 0xbb, 0xcc]);
+        let mut evm = Evm {
+            bytecode: Bytecode::from(&[OpcodeId::CALLDATACOPY.as_u8()][..]),
+            ..evm
+        };
+        // copy 4 bytes from calldata offset 1 to memory offset 1
+        for value in [4, 1, 1] {
+            evm.stack.try_push(U256::from(value)).unwrap();
+        }
+        evm.step().unwrap();
+        assert_eq!(evm.memory.raw_slice(0, 6), &[0, 0xbb, 0xcc, 0, 0, 0]);
+        assert_eq!(
+            evm.gas.used(),
+            GasCost::FASTEST + GasCost::COPY + Memory::gas_cost(1)
+        );
+
+        // zero-sized copies touch no memory
+        let result = exec_single(
+            OpcodeId::CALLDATACOPY,
+            &[!U256::zero(), !U256::zero(), U256::zero()],
+        )
+        .unwrap();
+        assert_eq!(result.memory.word_size(), 0);
+        assert_eq!(result.gas.used(), GasCost::FASTEST);
+    }
+
+    #[test]
+    fn test_codesize_and_codecopy() {
+        let mut evm = evm_with_code(
+            1000,
+            &[
+                (OpcodeId::CODESIZE, 0),
+                (OpcodeId::PUSH1, 2),
+                (OpcodeId::PUSH1, 0),
+                (OpcodeId::CODECOPY, 0),
+            ],
+        );
+        evm.run(10);
+        assert_eq!(evm.stack.inner, vec![]);
+        let code = evm.bytecode.to_bytes();
+        assert_eq!(code.len(), 6);
+        // CODESIZE bytes of code from offset 2, zero-padded past the end
+        let mut expected = code[2..].to_vec();
+        expected.resize(32, 0);
+        assert_eq!(evm.memory.raw_slice(0, 32), &expected[..]);
+        assert_eq!(
+            evm.gas.used(),
+            GasCost::QUICK
+                + GasCost::FASTEST
+                + GasCost::FASTEST
+                + GasCost::FASTEST
+                + GasCost::COPY
+                + Memory::gas_cost(1)
+        );
+    }
+
+    #[test]
+    fn test_env() {
+        let env = Env {
+            chain_id: 10,
+            block: BlockEnv {
+                coinbase: Address::repeat_byte(0xc0),
+                timestamp: 1_700_000_000,
+                number: 18_000_000,
+                prevrandao: U256::from(0x1234),
+                gas_limit: 30_000_000,
+                base_fee: U256::from(7),
+            },
+            tx: TxEnv {
+                origin: Address::repeat_byte(0x01),
+                gas_price: U256::from(9),
+                caller: Address::repeat_byte(0x02),
+                address: Address::repeat_byte(0x03),
+                value: U256::from(1000),
+                access_list: Vec::new(),
+            },
+        };
+        let expected = [
+            (
+                OpcodeId::ADDRESS,
+                address_to_word(Address::repeat_byte(0x03)),
+            ),
+            (
+                OpcodeId::ORIGIN,
+                address_to_word(Address::repeat_byte(0x01)),
+            ),
+            (
+                OpcodeId::CALLER,
+                address_to_word(Address::repeat_byte(0x02)),
+            ),
+            (OpcodeId::CALLVALUE, U256::from(1000)),
+            (OpcodeId::GASPRICE, U256::from(9)),
+            (
+                OpcodeId::COINBASE,
+                address_to_word(Address::repeat_byte(0xc0)),
+            ),
+            (OpcodeId::TIMESTAMP, U256::from(1_700_000_000)),
+            (OpcodeId::NUMBER, U256::from(18_000_000)),
+            (OpcodeId::DIFFICULTY, U256::from(0x1234)),
+            (OpcodeId::GASLIMIT, U256::from(30_000_000)),
+            (OpcodeId::CHAINID, U256::from(10)),
+            (OpcodeId::BASEFEE, U256::from(7)),
+        ];
+        for (opcode, value) in expected {
+            let mut evm = Evm::new(env.clone(), 1000);
+            evm.push_instruction(Instruction {
+                opcode,
+                push_data: None,
+            })
+            .unwrap();
+            evm.step().unwrap();
+            assert_eq!(evm.stack.inner, vec![value], "{:?}", opcode);
+            assert_eq!(evm.gas.used(), GasCost::QUICK);
+        }
+        assert_eq!(
+            address_to_word(Address::repeat_byte(0xff)),
+            (U256::one() << 160) - 1
+        );
+    }
+
+    #[test]
+    fn test_transient_storage() {
+        let mut evm = evm_with_code(
+            1000,
+            &[
+                (OpcodeId::PUSH1, 0x2a),
+                (OpcodeId::PUSH1, 1),
+                (OpcodeId::TSTORE, 0),
+                (OpcodeId::PUSH1, 1),
+                (OpcodeId::TLOAD, 0),
+                (OpcodeId::PUSH1, 2),
+                (OpcodeId::TLOAD, 0),
+            ],
+        );
+        evm.run(10);
+        assert_eq!(evm.stack.inner, vec![U256::from(0x2a), U256::zero()]);
+        let account = evm.world.account_mut(Address::zero());
+        assert_eq!(account.storage.raw_get(U256::one()), U256::zero());
+        assert_eq!(
+            account.transient_storage.raw_get(U256::one()),
+            U256::from(0x2a)
+        );
+        assert_eq!(
+            evm.gas.used(),
+            GasCost::from(GasCost::FASTEST.as_u64() * 4 + GasCost::WARM_ACCESS.as_u64() * 3)
+        );
+        evm.world.clear_transient_storage();
+        let account = evm.world.account_mut(Address::zero());
+        assert_eq!(account.transient_storage.raw_get(U256::one()), U256::zero());
+    }
+
+    #[test]
+    fn test_mcopy() {
+        // (dest_offset, offset, size, memory after)
+        let cases: [(u8, u8, u8, [u8; 8]); 4] = [
+            (0, 1, 3, [2, 3, 4, 4, 5, 6, 7, 8]),
+            (1, 0, 3, [1, 1, 2, 3, 5, 6, 7, 8]),
+            (2, 0, 6, [1, 2, 1, 2, 3, 4, 5, 6]),
+            (0, 0, 0, [1, 2, 3, 4, 5, 6, 7, 8]),
+        ];
+        for (dest_offset, offset, size, expected) in cases {
+            let mut evm = evm_with_code(1000, &[(OpcodeId::MCOPY, 0)]);
+            evm.memory
+                .copy_from(
+                    &[1, 2, 3, 4, 5, 6, 7, 8],
+                    U256::zero(),
+                    U256::zero(),
+                    U256::from(8),
+                    GasCost::ZERO,
+                    &mut evm.gas,
+                )
+                .unwrap();
+            for value in [size, offset, dest_offset] {
+                evm.stack.try_push(U256::from(value)).unwrap();
+            }
+            let gas_used = evm.gas.used();
+            evm.step().unwrap();
+            assert_eq!(evm.memory.raw_slice(0, 8), &expected);
+            assert_eq!(
+                evm.gas.used() - gas_used,
+                GasCost::FASTEST + GasCost::COPY.per_word(size as usize)
+            );
+        }
+
+        // copying past the end expands the memory
+        let evm = exec_single(
+            OpcodeId::MCOPY,
+            &[U256::zero(), U256::from(32), U256::from(1)],
+        )
+        .unwrap();
+        assert_eq!(evm.memory.word_size(), 2);
+        assert_eq!(
+            evm.gas.used(),
+            GasCost::FASTEST + GasCost::COPY + Memory::gas_cost(2)
+        );
+    }
 }
@@ -1,4 +1,5 @@
 use crate::evm::gas::GasCost;
+use crate::evm::SpecId;
 use once_cell::sync::Lazy;
 use rand::distributions::WeightedIndex;
 use rand::prelude::*;
@@ -6,10 +7,11 @@ use rand::RngCore;
 use strum::{EnumIter, IntoEnumIterator};
 
 /// Some opcodes are disabled in tetris generation.
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, EnumIter)]
 pub enum OpcodeId {
-    // /// `STOP`
-    // STOP,
+    /// `STOP`
+    STOP,
     /// `ADD`
     ADD,
     /// `MUL`
@@ -55,16 +57,16 @@ pub enum OpcodeId {
     /// `BYTE`
     BYTE,
 
-    // /// `CALLDATALOAD`
-    // CALLDATALOAD,
-    // /// `CALLDATASIZE`
-    // CALLDATASIZE,
-    // /// `CALLDATACOPY`
-    // CALLDATACOPY,
-    // /// `CODESIZE`
-    // CODESIZE,
-    // /// `CODECOPY`
-    // CODECOPY,
+    /// `CALLDATALOAD`
+    CALLDATALOAD,
+    /// `CALLDATASIZE`
+    CALLDATASIZE,
+    /// `CALLDATACOPY`
+    CALLDATACOPY,
+    /// `CODESIZE`
+    CODESIZE,
+    /// `CODECOPY`
+    CODECOPY,
     /// `SHL`
     SHL,
     /// `SHR`
@@ -80,16 +82,16 @@ pub enum OpcodeId {
     MSTORE,
     /// `MSTORE8`
     MSTORE8,
-    // /// `JUMP`
-    // JUMP,
-    // /// `JUMPI`
-    // JUMPI,
+    /// `JUMP`
+    JUMP,
+    /// `JUMPI`
+    JUMPI,
     /// `PC`
     PC,
     /// `MSIZE`
     MSIZE,
-    // /// `JUMPDEST`
-    // JUMPDEST,
+    /// `JUMPDEST`
+    JUMPDEST,
 
     // PUSHn
     /// `PUSH0`
@@ -227,87 +229,92 @@ pub enum OpcodeId {
     /// `SWAP16`
     SWAP16,
 
-    // /// `RETURN`
-    // RETURN,
-    // /// `REVERT`
-    // REVERT,
+    /// `RETURN`
+    RETURN,
+    /// `REVERT`
+    REVERT,
 
-    // /// Invalid opcode
-    // INVALID(u8),
+    /// Invalid opcode
+    INVALID(u8),
 
     // External opcodes
     /// `SHA3`
     SHA3,
-    // /// `ADDRESS`
-    // ADDRESS,
+    /// `ADDRESS`
+    ADDRESS,
     // /// `BALANCE`
     // BALANCE,
-    // /// `ORIGIN`
-    // ORIGIN,
-    // /// `CALLER`
-    // CALLER,
-    // /// `CALLVALUE`
-    // CALLVALUE,
-    // /// `GASPRICE`
-    // GASPRICE,
+    /// `ORIGIN`
+    ORIGIN,
+    /// `CALLER`
+    CALLER,
+    /// `CALLVALUE`
+    CALLVALUE,
+    /// `GASPRICE`
+    GASPRICE,
     // /// `EXTCODESIZE`
     // EXTCODESIZE,
     // /// `EXTCODECOPY`
     // EXTCODECOPY,
     // /// `EXTCODEHASH`
     // EXTCODEHASH,
-    // /// `RETURNDATASIZE`
-    // RETURNDATASIZE,
-    // /// `RETURNDATACOPY`
-    // RETURNDATACOPY,
+    /// `RETURNDATASIZE`
+    RETURNDATASIZE,
+    /// `RETURNDATACOPY`
+    RETURNDATACOPY,
     // /// `BLOCKHASH`
     // BLOCKHASH,
-    // /// `COINBASE`
-    // COINBASE,
-    // /// `TIMESTAMP`
-    // TIMESTAMP,
-    // /// `NUMBER`
-    // NUMBER,
-    // /// `DIFFICULTY`
-    // DIFFICULTY,
-    // /// `GASLIMIT`
-    // GASLIMIT,
-    // /// `CHAINID`
-    // CHAINID,
+    /// `COINBASE`
+    COINBASE,
+    /// `TIMESTAMP`
+    TIMESTAMP,
+    /// `NUMBER`
+    NUMBER,
+    /// `DIFFICULTY`, `PREVRANDAO` since the merge
+    DIFFICULTY,
+    /// `GASLIMIT`
+    GASLIMIT,
+    /// `CHAINID`
+    CHAINID,
     // /// `SELFBALANCE`
     // SELFBALANCE,
-    // /// `BASEFEE`
-    // BASEFEE,
+    /// `BASEFEE`
+    BASEFEE,
     /// `SLOAD`
     SLOAD,
     /// `SSTORE`
     SSTORE,
     /// `GAS`
     GAS,
+    /// `TLOAD`
+    TLOAD,
+    /// `TSTORE`
+    TSTORE,
+    /// `MCOPY`
+    MCOPY,
     // LOGn
-    // /// `LOG0`
-    // LOG0,
-    // /// `LOG1`
-    // LOG1,
-    // /// `LOG2`
-    // LOG2,
-    // /// `LOG3`
-    // LOG3,
-    // /// `LOG4`
-    // LOG4,
-
-    // /// `CREATE`
-    // CREATE,
-    // /// `CREATE2`
-    // CREATE2,
-    // /// `CALL`
-    // CALL,
-    // /// `CALLCODE`
-    // CALLCODE,
-    // /// `DELEGATECALL`
-    // DELEGATECALL,
-    // /// `STATICCALL`
-    // STATICCALL,
+    /// `LOG0`
+    LOG0,
+    /// `LOG1`
+    LOG1,
+    /// `LOG2`
+    LOG2,
+    /// `LOG3`
+    LOG3,
+    /// `LOG4`
+    LOG4,
+    /// `CREATE`
+    CREATE,
+    /// `CREATE2`
+    CREATE2,
+    /// `CALL`
+    CALL,
+    /// `CALLCODE`
+    CALLCODE,
+    /// `DELEGATECALL`
+    DELEGATECALL,
+    /// `STATICCALL`
+    STATICCALL,
     // /// `SELFDESTRUCT`
     // SELFDESTRUCT,
 }
@@ -334,16 +341,25 @@ const OPCODE_ID_FREQ: [usize; 256] = [
     33503, 1103, 1423, 864130, 32426, 1040,
 ];
 
-static OPCODES: Lazy<Vec<OpcodeId>> = Lazy::new(|| OpcodeId::iter().collect());
+static OPCODES: Lazy<Vec<OpcodeId>> = Lazy::new(|| {
+    OpcodeId::iter()
+        .filter(|opcode| opcode.is_generated())
+        .collect()
+});
 static OPCODE_DIST: Lazy<WeightedIndex<usize>> = Lazy::new(|| {
-    WeightedIndex::new(OpcodeId::iter().map(|opcode| OPCODE_ID_FREQ[opcode.as_usize()])).unwrap()
+    WeightedIndex::new(
+        OPCODES
+            .iter()
+            .map(|opcode| OPCODE_ID_FREQ[opcode.as_usize()]),
+    )
+    .unwrap()
 });
 
 impl OpcodeId {
     /// Returns the `OpcodeId` as a `u8`.
     pub const fn as_u8(&self) -> u8 {
         match self {
-            // OpcodeId::STOP => 0x00u8,
+            OpcodeId::STOP => 0x00u8,
             OpcodeId::ADD => 0x01u8,
             OpcodeId::MUL => 0x02u8,
             OpcodeId::SUB => 0x03u8,
@@ -366,11 +382,11 @@ impl OpcodeId {
             OpcodeId::XOR => 0x18u8,
             OpcodeId::NOT => 0x19u8,
             OpcodeId::BYTE => 0x1au8,
-            // OpcodeId::CALLDATALOAD => 0x35u8,
-            // OpcodeId::CALLDATASIZE => 0x36u8,
-            // OpcodeId::CALLDATACOPY => 0x37u8,
-            // OpcodeId::CODESIZE => 0x38u8,
-            // OpcodeId::CODECOPY => 0x39u8,
+            OpcodeId::CALLDATALOAD => 0x35u8,
+            OpcodeId::CALLDATASIZE => 0x36u8,
+            OpcodeId::CALLDATACOPY => 0x37u8,
+            OpcodeId::CODESIZE => 0x38u8,
+            OpcodeId::CODECOPY => 0x39u8,
             OpcodeId::SHL => 0x1bu8,
             OpcodeId::SHR => 0x1cu8,
             OpcodeId::SAR => 0x1du8,
@@ -378,11 +394,11 @@ impl OpcodeId {
             OpcodeId::MLOAD => 0x51u8,
             OpcodeId::MSTORE => 0x52u8,
             OpcodeId::MSTORE8 => 0x53u8,
-            // OpcodeId::JUMP => 0x56u8,
-            // OpcodeId::JUMPI => 0x57u8,
+            OpcodeId::JUMP => 0x56u8,
+            OpcodeId::JUMPI => 0x57u8,
             OpcodeId::PC => 0x58u8,
             OpcodeId::MSIZE => 0x59u8,
-            // OpcodeId::JUMPDEST => 0x5bu8,
+            OpcodeId::JUMPDEST => 0x5bu8,
             OpcodeId::PUSH0 => 0x5fu8,
             OpcodeId::PUSH1 => 0x60u8,
             OpcodeId::PUSH2 => 0x61u8,
@@ -448,44 +464,47 @@ impl OpcodeId {
             OpcodeId::SWAP14 => 0x9du8,
             OpcodeId::SWAP15 => 0x9eu8,
             OpcodeId::SWAP16 => 0x9fu8,
-            // OpcodeId::RETURN => 0xf3u8,
-            // OpcodeId::REVERT => 0xfdu8,
-            // OpcodeId::INVALID(b) => *b,
+            OpcodeId::RETURN => 0xf3u8,
+            OpcodeId::REVERT => 0xfdu8,
+            OpcodeId::INVALID(b) => *b,
             OpcodeId::SHA3 => 0x20u8,
-            // OpcodeId::ADDRESS => 0x30u8,
+            OpcodeId::ADDRESS => 0x30u8,
             // OpcodeId::BALANCE => 0x31u8,
-            // OpcodeId::ORIGIN => 0x32u8,
-            // OpcodeId::CALLER => 0x33u8,
-            // OpcodeId::CALLVALUE => 0x34u8,
-            // OpcodeId::GASPRICE => 0x3au8,
+            OpcodeId::ORIGIN => 0x32u8,
+            OpcodeId::CALLER => 0x33u8,
+            OpcodeId::CALLVALUE => 0x34u8,
+            OpcodeId::GASPRICE => 0x3au8,
             // OpcodeId::EXTCODESIZE => 0x3bu8,
             // OpcodeId::EXTCODECOPY => 0x3cu8,
             // OpcodeId::EXTCODEHASH => 0x3fu8,
-            // OpcodeId::RETURNDATASIZE => 0x3du8,
-            // OpcodeId::RETURNDATACOPY => 0x3eu8,
+            OpcodeId::RETURNDATASIZE => 0x3du8,
+            OpcodeId::RETURNDATACOPY => 0x3eu8,
             // OpcodeId::BLOCKHASH => 0x40u8,
-            // OpcodeId::COINBASE => 0x41u8,
-            // OpcodeId::TIMESTAMP => 0x42u8,
-            // OpcodeId::NUMBER => 0x43u8,
-            // OpcodeId::DIFFICULTY => 0x44u8,
-            // OpcodeId::GASLIMIT => 0x45u8,
-            // OpcodeId::CHAINID => 0x46u8,
+            OpcodeId::COINBASE => 0x41u8,
+            OpcodeId::TIMESTAMP => 0x42u8,
+            OpcodeId::NUMBER => 0x43u8,
+            OpcodeId::DIFFICULTY => 0x44u8,
+            OpcodeId::GASLIMIT => 0x45u8,
+            OpcodeId::CHAINID => 0x46u8,
             // OpcodeId::SELFBALANCE => 0x47u8,
-            // OpcodeId::BASEFEE => 0x48u8,
+            OpcodeId::BASEFEE => 0x48u8,
             OpcodeId::SLOAD => 0x54u8,
             OpcodeId::SSTORE => 0x55u8,
             OpcodeId::GAS => 0x5au8,
-            // OpcodeId::LOG0 => 0xa0u8,
-            // OpcodeId::LOG1 => 0xa1u8,
-            // OpcodeId::LOG2 => 0xa2u8,
-            // OpcodeId::LOG3 => 0xa3u8,
-            // OpcodeId::LOG4 => 0xa4u8,
-            // OpcodeId::CREATE => 0xf0u8,
-            // OpcodeId::CREATE2 => 0xf5u8,
-            // OpcodeId::CALL => 0xf1u8,
-            // OpcodeId::CALLCODE => 0xf2u8,
-            // OpcodeId::DELEGATECALL => 0xf4u8,
-            // OpcodeId::STATICCALL => 0xfau8,
+            OpcodeId::TLOAD => 0x5cu8,
+            OpcodeId::TSTORE => 0x5du8,
+            OpcodeId::MCOPY => 0x5eu8,
+            OpcodeId::LOG0 => 0xa0u8,
+            OpcodeId::LOG1 => 0xa1u8,
+            OpcodeId::LOG2 => 0xa2u8,
+            OpcodeId::LOG3 => 0xa3u8,
+            OpcodeId::LOG4 => 0xa4u8,
+            OpcodeId::CREATE => 0xf0u8,
+            OpcodeId::CREATE2 => 0xf5u8,
+            OpcodeId::CALL => 0xf1u8,
+            OpcodeId::CALLCODE => 0xf2u8,
+            OpcodeId::DELEGATECALL => 0xf4u8,
+            OpcodeId::STATICCALL => 0xfau8,
             // OpcodeId::SELFDESTRUCT => 0xffu8,
         }
     }
@@ -495,10 +514,10 @@ impl OpcodeId {
         self.as_u8() as usize
     }
 
-    /// Returns the constant gas cost of `OpcodeId`
+    /// Returns the constant gas cost of `OpcodeId` under the latest fork
     pub const fn constant_gas_cost(&self) -> GasCost {
         match self {
-            // OpcodeId::STOP => GasCost::ZERO,
+            OpcodeId::STOP => GasCost::ZERO,
             OpcodeId::ADD => GasCost::FASTEST,
             OpcodeId::MUL => GasCost::FAST,
             OpcodeId::SUB => GasCost::FASTEST,
@@ -525,43 +544,46 @@ impl OpcodeId {
             OpcodeId::SHR => GasCost::FASTEST,
             OpcodeId::SAR => GasCost::FASTEST,
             OpcodeId::SHA3 => GasCost::SHA3,
-            // OpcodeId::ADDRESS => GasCost::QUICK,
+            OpcodeId::ADDRESS => GasCost::QUICK,
             // OpcodeId::BALANCE => GasCost::WARM_ACCESS,
-            // OpcodeId::ORIGIN => GasCost::QUICK,
-            // OpcodeId::CALLER => GasCost::QUICK,
-            // OpcodeId::CALLVALUE => GasCost::QUICK,
-            // OpcodeId::CALLDATALOAD => GasCost::FASTEST,
-            // OpcodeId::CALLDATASIZE => GasCost::QUICK,
-            // OpcodeId::CALLDATACOPY => GasCost::FASTEST,
-            // OpcodeId::CODESIZE => GasCost::QUICK,
-            // OpcodeId::CODECOPY => GasCost::FASTEST,
-            // OpcodeId::GASPRICE => GasCost::QUICK,
+            OpcodeId::ORIGIN => GasCost::QUICK,
+            OpcodeId::CALLER => GasCost::QUICK,
+            OpcodeId::CALLVALUE => GasCost::QUICK,
+            OpcodeId::CALLDATALOAD => GasCost::FASTEST,
+            OpcodeId::CALLDATASIZE => GasCost::QUICK,
+            OpcodeId::CALLDATACOPY => GasCost::FASTEST,
+            OpcodeId::CODESIZE => GasCost::QUICK,
+            OpcodeId::CODECOPY => GasCost::FASTEST,
+            OpcodeId::GASPRICE => GasCost::QUICK,
             // OpcodeId::EXTCODESIZE => GasCost::WARM_ACCESS,
             // OpcodeId::EXTCODECOPY => GasCost::WARM_ACCESS,
-            // OpcodeId::RETURNDATASIZE => GasCost::QUICK,
-            // OpcodeId::RETURNDATACOPY => GasCost::FASTEST,
+            OpcodeId::RETURNDATASIZE => GasCost::QUICK,
+            OpcodeId::RETURNDATACOPY => GasCost::FASTEST,
             // OpcodeId::EXTCODEHASH => GasCost::WARM_ACCESS,
             // OpcodeId::BLOCKHASH => GasCost::EXT,
-            // OpcodeId::COINBASE => GasCost::QUICK,
-            // OpcodeId::TIMESTAMP => GasCost::QUICK,
-            // OpcodeId::NUMBER => GasCost::QUICK,
-            // OpcodeId::DIFFICULTY => GasCost::QUICK,
-            // OpcodeId::GASLIMIT => GasCost::QUICK,
-            // OpcodeId::CHAINID => GasCost::QUICK,
+            OpcodeId::COINBASE => GasCost::QUICK,
+            OpcodeId::TIMESTAMP => GasCost::QUICK,
+            OpcodeId::NUMBER => GasCost::QUICK,
+            OpcodeId::DIFFICULTY => GasCost::QUICK,
+            OpcodeId::GASLIMIT => GasCost::QUICK,
+            OpcodeId::CHAINID => GasCost::QUICK,
             // OpcodeId::SELFBALANCE => GasCost::FAST,
-            // OpcodeId::BASEFEE => GasCost::QUICK,
+            OpcodeId::BASEFEE => GasCost::QUICK,
             OpcodeId::POP => GasCost::QUICK,
             OpcodeId::MLOAD => GasCost::FASTEST,
             OpcodeId::MSTORE => GasCost::FASTEST,
             OpcodeId::MSTORE8 => GasCost::FASTEST,
             OpcodeId::SLOAD => GasCost::ZERO,
             OpcodeId::SSTORE => GasCost::ZERO,
-            // OpcodeId::JUMP => GasCost::MID,
-            // OpcodeId::JUMPI => GasCost::SLOW,
+            OpcodeId::JUMP => GasCost::MID,
+            OpcodeId::JUMPI => GasCost::SLOW,
             OpcodeId::PC => GasCost::QUICK,
             OpcodeId::MSIZE => GasCost::QUICK,
             OpcodeId::GAS => GasCost::QUICK,
-            // OpcodeId::JUMPDEST => GasCost::ONE,
+            OpcodeId::TLOAD => GasCost::WARM_ACCESS,
+            OpcodeId::TSTORE => GasCost::WARM_ACCESS,
+            OpcodeId::MCOPY => GasCost::FASTEST,
+            OpcodeId::JUMPDEST => GasCost::ONE,
             OpcodeId::PUSH0 => GasCost::QUICK,
             OpcodeId::PUSH1 => GasCost::FASTEST,
             OpcodeId::PUSH2 => GasCost::FASTEST,
@@ -627,24 +649,126 @@ impl OpcodeId {
             OpcodeId::SWAP14 => GasCost::FASTEST,
             OpcodeId::SWAP15 => GasCost::FASTEST,
             OpcodeId::SWAP16 => GasCost::FASTEST,
-            // OpcodeId::LOG0 => GasCost::ZERO,
-            // OpcodeId::LOG1 => GasCost::ZERO,
-            // OpcodeId::LOG2 => GasCost::ZERO,
-            // OpcodeId::LOG3 => GasCost::ZERO,
-            // OpcodeId::LOG4 => GasCost::ZERO,
-            // OpcodeId::CREATE => GasCost::CREATE,
-            // OpcodeId::CALL => GasCost::WARM_ACCESS,
-            // OpcodeId::CALLCODE => GasCost::WARM_ACCESS,
-            // OpcodeId::RETURN => GasCost::ZERO,
-            // OpcodeId::DELEGATECALL => GasCost::WARM_ACCESS,
-            // OpcodeId::CREATE2 => GasCost::CREATE,
-            // OpcodeId::STATICCALL => GasCost::WARM_ACCESS,
-            // OpcodeId::REVERT => GasCost::ZERO,
-            // OpcodeId::INVALID(_) => GasCost::ZERO,
+            OpcodeId::LOG0 => GasCost::ZERO,
+            OpcodeId::LOG1 => GasCost::ZERO,
+            OpcodeId::LOG2 => GasCost::ZERO,
+            OpcodeId::LOG3 => GasCost::ZERO,
+            OpcodeId::LOG4 => GasCost::ZERO,
+            OpcodeId::CREATE => GasCost::CREATE,
+            OpcodeId::CALL => GasCost::WARM_ACCESS,
+            OpcodeId::CALLCODE => GasCost::WARM_ACCESS,
+            OpcodeId::RETURN => GasCost::ZERO,
+            OpcodeId::DELEGATECALL => GasCost::WARM_ACCESS,
+            OpcodeId::CREATE2 => GasCost::CREATE,
+            OpcodeId::STATICCALL => GasCost::WARM_ACCESS,
+            OpcodeId::REVERT => GasCost::ZERO,
+            OpcodeId::INVALID(_) => GasCost::ZERO,
             // OpcodeId::SELFDESTRUCT => GasCost::SELFDESTRUCT,
         }
     }
 
+    /// Returns the constant gas cost of `OpcodeId` under the rules of `spec`
+    pub const fn constant_gas_cost_in(&self, spec: SpecId) -> GasCost {
+        match self {
+            OpcodeId::SLOAD if !spec.is_enabled_in(SpecId::Tangerine) => GasCost::SLOAD_FRONTIER,
+            OpcodeId::SLOAD if !spec.is_enabled_in(SpecId::Istanbul) => GasCost::SLOAD_TANGERINE,
+            // EIP-2929 moved the cost of SLOAD to the warm or cold access
+            OpcodeId::SLOAD if !spec.is_enabled_in(SpecId::Berlin) => GasCost::SLOAD_ISTANBUL,
+            OpcodeId::CALL | OpcodeId::CALLCODE | OpcodeId::DELEGATECALL | OpcodeId::STATICCALL
+                if !spec.is_enabled_in(SpecId::Tangerine) =>
+            {
+                GasCost::CALL_FRONTIER
+            }
+            OpcodeId::CALL | OpcodeId::CALLCODE | OpcodeId::DELEGATECALL | OpcodeId::STATICCALL
+                if !spec.is_enabled_in(SpecId::Berlin) =>
+            {
+                GasCost::CALL_TANGERINE
+            }
+            _ => self.constant_gas_cost(),
+        }
+    }
+
+    /// Returns the fork which introduced `OpcodeId`
+    pub const fn introduced_in(&self) -> SpecId {
+        match self {
+            OpcodeId::DELEGATECALL => SpecId::Homestead,
+            OpcodeId::RETURNDATASIZE
+            | OpcodeId::RETURNDATACOPY
+            | OpcodeId::STATICCALL
+            | OpcodeId::REVERT => SpecId::Byzantium,
+            OpcodeId::SHL | OpcodeId::SHR | OpcodeId::SAR | OpcodeId::CREATE2 => {
+                SpecId::Constantinople
+            }
+            OpcodeId::CHAINID => SpecId::Istanbul,
+            OpcodeId::BASEFEE => SpecId::London,
+            OpcodeId::PUSH0 => SpecId::Shanghai,
+            OpcodeId::TLOAD | OpcodeId::TSTORE | OpcodeId::MCOPY => SpecId::Cancun,
+            _ => SpecId::Frontier,
+        }
+    }
+
+    /// Returns `true` if `OpcodeId` exists under the rules of `spec`.
+    pub const fn is_enabled_in(&self, spec: SpecId) -> bool {
+        !self.is_invalid() && spec.is_enabled_in(self.introduced_in())
+    }
+
+    /// Returns the number of stack items the `OpcodeId` pops or reads
+    pub const fn stack_inputs(&self) -> usize {
+        match self {
+            _ if self.is_dup() => self.as_usize() - Self::DUP1.as_usize() + 1,
+            _ if self.is_swap() => self.as_usize() - Self::SWAP1.as_usize() + 2,
+            _ if self.is_log() => self.as_usize() - Self::LOG0.as_usize() + 2,
+            OpcodeId::ISZERO
+            | OpcodeId::NOT
+            | OpcodeId::CALLDATALOAD
+            | OpcodeId::POP
+            | OpcodeId::MLOAD
+            | OpcodeId::JUMP
+            | OpcodeId::SLOAD
+            | OpcodeId::TLOAD => 1,
+            OpcodeId::ADD
+            | OpcodeId::MUL
+            | OpcodeId::SUB
+            | OpcodeId::DIV
+            | OpcodeId::SDIV
+            | OpcodeId::MOD
+            | OpcodeId::SMOD
+            | OpcodeId::EXP
+            | OpcodeId::SIGNEXTEND
+            | OpcodeId::LT
+            | OpcodeId::GT
+            | OpcodeId::SLT
+            | OpcodeId::SGT
+            | OpcodeId::EQ
+            | OpcodeId::AND
+            | OpcodeId::OR
+            | OpcodeId::XOR
+            | OpcodeId::BYTE
+            | OpcodeId::SHL
+            | OpcodeId::SHR
+            | OpcodeId::SAR
+            | OpcodeId::MSTORE
+            | OpcodeId::MSTORE8
+            | OpcodeId::JUMPI
+            | OpcodeId::RETURN
+            | OpcodeId::REVERT
+            | OpcodeId::SHA3
+            | OpcodeId::SSTORE
+            | OpcodeId::TSTORE => 2,
+            OpcodeId::ADDMOD
+            | OpcodeId::MULMOD
+            | OpcodeId::CALLDATACOPY
+            | OpcodeId::CODECOPY
+            | OpcodeId::RETURNDATACOPY
+            | OpcodeId::MCOPY
+            | OpcodeId::CREATE => 3,
+            OpcodeId::CREATE2 => 4,
+            OpcodeId::DELEGATECALL | OpcodeId::STATICCALL => 6,
+            OpcodeId::CALL | OpcodeId::CALLCODE => 7,
+            _ => 0,
+        }
+    }
+
     /// Returns `true` if the `OpcodeId` is a `PUSHn` (including `PUSH0`).
     pub const fn is_push(&self) -> bool {
         self.as_u8() >= Self::PUSH0.as_u8() && self.as_u8() <= Self::PUSH32.as_u8()
@@ -660,6 +784,62 @@ impl OpcodeId {
         self.as_u8() >= Self::SWAP1.as_u8() && self.as_u8() <= Self::SWAP16.as_u8()
     }
 
+    /// Returns `true` if the `OpcodeId` is a `LOGn`.
+    pub const fn is_log(&self) -> bool {
+        self.as_u8() >= Self::LOG0.as_u8() && self.as_u8() <= Self::LOG4.as_u8()
+    }
+
+    /// Returns `true` if the `OpcodeId` is an invalid or disabled opcode.
+    pub const fn is_invalid(&self) -> bool {
+        matches!(self, Self::INVALID(_))
+    }
+
+    /// Returns `true` if the `OpcodeId` can be dealt by `random`: the arithmetic,
+    /// stack, memory and storage opcodes, but no control flow, environment or system ones.
+    pub const fn is_generated(&self) -> bool {
+        self.is_push()
+            || self.is_dup()
+            || self.is_swap()
+            || matches!(
+                self,
+                Self::ADD
+                    | Self::MUL
+                    | Self::SUB
+                    | Self::DIV
+                    | Self::SDIV
+                    | Self::MOD
+                    | Self::SMOD
+                    | Self::ADDMOD
+                    | Self::MULMOD
+                    | Self::EXP
+                    | Self::SIGNEXTEND
+                    | Self::LT
+                    | Self::GT
+                    | Self::SLT
+                    | Self::SGT
+                    | Self::EQ
+                    | Self::ISZERO
+                    | Self::AND
+                    | Self::OR
+                    | Self::XOR
+                    | Self::NOT
+                    | Self::BYTE
+                    | Self::SHL
+                    | Self::SHR
+                    | Self::SAR
+                    | Self::POP
+                    | Self::MLOAD
+                    | Self::MSTORE
+                    | Self::MSTORE8
+                    | Self::PC
+                    | Self::MSIZE
+                    | Self::SHA3
+                    | Self::SLOAD
+                    | Self::SSTORE
+                    | Self::GAS
+            )
+    }
+
     pub fn random(mut rng: impl RngCore) -> Self {
         OPCODES[OPCODE_DIST.sample(&mut rng)]
     }
@@ -668,7 +848,7 @@ impl OpcodeId {
 impl From<u8> for OpcodeId {
     fn from(value: u8) -> Self {
         match value {
-            // 0x00u8 => OpcodeId::STOP,
+            0x00u8 => OpcodeId::STOP,
             0x01u8 => OpcodeId::ADD,
             0x02u8 => OpcodeId::MUL,
             0x03u8 => OpcodeId::SUB,
@@ -691,11 +871,11 @@ impl From<u8> for OpcodeId {
             0x18u8 => OpcodeId::XOR,
             0x19u8 => OpcodeId::NOT,
             0x1au8 => OpcodeId::BYTE,
-            // 0x35u8 => OpcodeId::CALLDATALOAD,
-            // 0x36u8 => OpcodeId::CALLDATASIZE,
-            // 0x37u8 => OpcodeId::CALLDATACOPY,
-            // 0x38u8 => OpcodeId::CODESIZE,
-            // 0x39u8 => OpcodeId::CODECOPY,
+            0x35u8 => OpcodeId::CALLDATALOAD,
+            0x36u8 => OpcodeId::CALLDATASIZE,
+            0x37u8 => OpcodeId::CALLDATACOPY,
+            0x38u8 => OpcodeId::CODESIZE,
+            0x39u8 => OpcodeId::CODECOPY,
             0x1bu8 => OpcodeId::SHL,
             0x1cu8 => OpcodeId::SHR,
             0x1du8 => OpcodeId::SAR,
@@ -703,11 +883,11 @@ impl From<u8> for OpcodeId {
             0x51u8 => OpcodeId::MLOAD,
             0x52u8 => OpcodeId::MSTORE,
             0x53u8 => OpcodeId::MSTORE8,
-            // 0x56u8 => OpcodeId::JUMP,
-            // 0x57u8 => OpcodeId::JUMPI,
+            0x56u8 => OpcodeId::JUMP,
+            0x57u8 => OpcodeId::JUMPI,
             0x58u8 => OpcodeId::PC,
             0x59u8 => OpcodeId::MSIZE,
-            // 0x5bu8 => OpcodeId::JUMPDEST,
+            0x5bu8 => OpcodeId::JUMPDEST,
             0x5fu8 => OpcodeId::PUSH0,
             0x60u8 => OpcodeId::PUSH1,
             0x61u8 => OpcodeId::PUSH2,
@@ -773,47 +953,150 @@ impl From<u8> for OpcodeId {
             0x9du8 => OpcodeId::SWAP14,
             0x9eu8 => OpcodeId::SWAP15,
             0x9fu8 => OpcodeId::SWAP16,
-            // 0xf3u8 => OpcodeId::RETURN,
-            // 0xfdu8 => OpcodeId::REVERT,
-            // 0xfeu8 => OpcodeId::INVALID(value),
+            0xf3u8 => OpcodeId::RETURN,
+            0xfdu8 => OpcodeId::REVERT,
             0x20u8 => OpcodeId::SHA3,
-            // 0x30u8 => OpcodeId::ADDRESS,
+            0x30u8 => OpcodeId::ADDRESS,
             // 0x31u8 => OpcodeId::BALANCE,
-            // 0x32u8 => OpcodeId::ORIGIN,
-            // 0x33u8 => OpcodeId::CALLER,
-            // 0x34u8 => OpcodeId::CALLVALUE,
-            // 0x3au8 => OpcodeId::GASPRICE,
+            0x32u8 => OpcodeId::ORIGIN,
+            0x33u8 => OpcodeId::CALLER,
+            0x34u8 => OpcodeId::CALLVALUE,
+            0x3au8 => OpcodeId::GASPRICE,
             // 0x3bu8 => OpcodeId::EXTCODESIZE,
             // 0x3cu8 => OpcodeId::EXTCODECOPY,
             // 0x3fu8 => OpcodeId::EXTCODEHASH,
-            // 0x3du8 => OpcodeId::RETURNDATASIZE,
-            // 0x3eu8 => OpcodeId::RETURNDATACOPY,
+            0x3du8 => OpcodeId::RETURNDATASIZE,
+            0x3eu8 => OpcodeId::RETURNDATACOPY,
             // 0x40u8 => OpcodeId::BLOCKHASH,
-            // 0x41u8 => OpcodeId::COINBASE,
-            // 0x42u8 => OpcodeId::TIMESTAMP,
-            // 0x43u8 => OpcodeId::NUMBER,
-            // 0x44u8 => OpcodeId::DIFFICULTY,
-            // 0x45u8 => OpcodeId::GASLIMIT,
-            // 0x46u8 => OpcodeId::CHAINID,
+            0x41u8 => OpcodeId::COINBASE,
+            0x42u8 => OpcodeId::TIMESTAMP,
+            0x43u8 => OpcodeId::NUMBER,
+            0x44u8 => OpcodeId::DIFFICULTY,
+            0x45u8 => OpcodeId::GASLIMIT,
+            0x46u8 => OpcodeId::CHAINID,
             // 0x47u8 => OpcodeId::SELFBALANCE,
-            // 0x48u8 => OpcodeId::BASEFEE,
+            0x48u8 => OpcodeId::BASEFEE,
             0x54u8 => OpcodeId::SLOAD,
             0x55u8 => OpcodeId::SSTORE,
             0x5au8 => OpcodeId::GAS,
-            // 0xa0u8 => OpcodeId::LOG0,
-            // 0xa1u8 => OpcodeId::LOG1,
-            // 0xa2u8 => OpcodeId::LOG2,
-            // 0xa3u8 => OpcodeId::LOG3,
-            // 0xa4u8 => OpcodeId::LOG4,
-            // 0xf0u8 => OpcodeId::CREATE,
-            // 0xf5u8 => OpcodeId::CREATE2,
-            // 0xf1u8 => OpcodeId::CALL,
-            // 0xf2u8 => OpcodeId::CALLCODE,
-            // 0xf4u8 => OpcodeId::DELEGATECALL,
-            // 0xfau8 => OpcodeId::STATICCALL,
+            0x5cu8 => OpcodeId::TLOAD,
+            0x5du8 => OpcodeId::TSTORE,
+            0x5eu8 => OpcodeId::MCOPY,
+            0xa0u8 => OpcodeId::LOG0,
+            0xa1u8 => OpcodeId::LOG1,
+            0xa2u8 => OpcodeId::LOG2,
+            0xa3u8 => OpcodeId::LOG3,
+            0xa4u8 => OpcodeId::LOG4,
+            0xf0u8 => OpcodeId::CREATE,
+            0xf5u8 => OpcodeId::CREATE2,
+            0xf1u8 => OpcodeId::CALL,
+            0xf2u8 => OpcodeId::CALLCODE,
+            0xf4u8 => OpcodeId::DELEGATECALL,
+            0xfau8 => OpcodeId::STATICCALL,
             // 0xffu8 => OpcodeId::SELFDESTRUCT,
-            // b => OpcodeId::INVALID(b),
-            _ => panic!("Invalid or Disabled opcode: {}", value),
+            b => OpcodeId::INVALID(b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_u8_round_trip() {
+        for value in 0..=u8::MAX {
+            let opcode = OpcodeId::from(value);
+            assert_eq!(opcode.as_u8(), value);
+        }
+        assert_eq!(OpcodeId::from(0xfe), OpcodeId::INVALID(0xfe));
+        assert_eq!(OpcodeId::from(0x0c), OpcodeId::INVALID(0x0c));
+    }
+
+    #[test]
+    fn test_enabled_in() {
+        assert!(!OpcodeId::PUSH0.is_enabled_in(SpecId::London));
+        assert!(OpcodeId::PUSH0.is_enabled_in(SpecId::Shanghai));
+        assert!(!OpcodeId::SHL.is_enabled_in(SpecId::Byzantium));
+        assert!(OpcodeId::SHL.is_enabled_in(SpecId::Constantinople));
+        assert!(!OpcodeId::TSTORE.is_enabled_in(SpecId::Shanghai));
+        assert!(OpcodeId::ADD.is_enabled_in(SpecId::Frontier));
+        assert!(!OpcodeId::INVALID(0xfe).is_enabled_in(SpecId::Cancun));
+        // every valid opcode exists in the latest fork
+        for opcode in OpcodeId::iter().filter(|opcode| !opcode.is_invalid()) {
+            assert!(opcode.is_enabled_in(SpecId::Cancun));
+            assert_eq!(
+                opcode.constant_gas_cost_in(SpecId::Cancun),
+                opcode.constant_gas_cost()
+            );
+        }
+    }
+
+    #[test]
+    fn test_constant_gas_cost_in() {
+        let sload: Vec<_> = [
+            SpecId::Frontier,
+            SpecId::Tangerine,
+            SpecId::Istanbul,
+            SpecId::Berlin,
+        ]
+        .into_iter()
+        .map(|spec| OpcodeId::SLOAD.constant_gas_cost_in(spec).as_u64())
+        .collect();
+        assert_eq!(sload, vec![50, 200, 800, 0]);
+        assert_eq!(
+            OpcodeId::CALL.constant_gas_cost_in(SpecId::Istanbul),
+            GasCost::CALL_TANGERINE
+        );
+        assert_eq!(
+            OpcodeId::STATICCALL.constant_gas_cost_in(SpecId::London),
+            GasCost::WARM_ACCESS
+        );
+    }
+
+    #[test]
+    fn test_stack_inputs() {
+        for (opcode, inputs) in [
+            (OpcodeId::STOP, 0),
+            (OpcodeId::PUSH32, 0),
+            (OpcodeId::ISZERO, 1),
+            (OpcodeId::ADD, 2),
+            (OpcodeId::MULMOD, 3),
+            (OpcodeId::DUP1, 1),
+            (OpcodeId::DUP16, 16),
+            (OpcodeId::SWAP3, 4),
+            (OpcodeId::SWAP16, 17),
+            (OpcodeId::LOG0, 2),
+            (OpcodeId::LOG4, 6),
+            (OpcodeId::CREATE2, 4),
+            (OpcodeId::STATICCALL, 6),
+            (OpcodeId::CALL, 7),
+        ] {
+            assert_eq!(opcode.stack_inputs(), inputs, "{:?}", opcode);
+        }
+    }
+
+    #[test]
+    fn test_random_never_invalid() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for _ in 0..10000 {
+            let opcode = OpcodeId::random(&mut rng);
+            assert!(!opcode.is_invalid());
+            assert!(opcode.is_generated(), "{:?}", opcode);
+        }
+        // the generator deals the opcodes it always did
+        assert_eq!(OPCODES.len(), 100);
+        for opcode in [
+            OpcodeId::STOP,
+            OpcodeId::JUMP,
+            OpcodeId::CALLDATALOAD,
+            OpcodeId::LOG0,
+            OpcodeId::RETURN,
+            OpcodeId::CALL,
+            OpcodeId::CREATE,
+            OpcodeId::TSTORE,
+        ] {
+            assert!(!opcode.is_generated(), "{:?}", opcode);
         }
     }
 }
@@ -0,0 +1,59 @@
+use crate::evm::{EvmError, ExecutionError, GasCost};
+
+/// The reason why the execution stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HaltReason {
+    /// `STOP`, or running off the end of the code
+    Stop,
+    /// `RETURN`
+    Return,
+    /// `REVERT`
+    Revert,
+    /// exceptional halt, all gas is consumed
+    Exception(ExecutionError),
+    /// the step limit was reached before the execution halted
+    StepLimitReached,
+}
+
+impl HaltReason {
+    /// Returns the error of an exceptional halt
+    pub fn error(&self) -> Option<&EvmError> {
+        match self {
+            HaltReason::Exception(e) => Some(&e.error),
+            _ => None,
+        }
+    }
+}
+
+/// The end state of an execution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionOutcome {
+    /// Why the execution stopped
+    pub halt_reason: HaltReason,
+    /// Data returned by `RETURN` or `REVERT`
+    pub return_data: Vec<u8>,
+    /// Gas used, before refunds
+    pub gas_used: GasCost,
+    /// Gas refunded, capped by the fork's refund quotient.
+    /// Zero unless the execution halted with `STOP` or `RETURN`.
+    pub gas_refunded: GasCost,
+    /// Gas left
+    pub gas_left: GasCost,
+}
+
+impl ExecutionOutcome {
+    /// Returns `true` if the execution halted with `STOP` or `RETURN`
+    pub fn is_success(&self) -> bool {
+        matches!(self.halt_reason, HaltReason::Stop | HaltReason::Return)
+    }
+
+    /// Returns the gas used after refunds, which the sender pays for
+    pub fn gas_used_after_refund(&self) -> GasCost {
+        self.gas_used - self.gas_refunded
+    }
+
+    /// Returns `true` if the execution halted with `REVERT`
+    pub fn is_revert(&self) -> bool {
+        self.halt_reason == HaltReason::Revert
+    }
+}
@@ -0,0 +1,215 @@
+use crate::evm::precompiles::{right_pad, Precompile};
+use crate::evm::{EvmError, GasCost};
+use bn::{AffineG1, AffineG2, Fq, Fq2, Fr, Group, Gt, G1, G2};
+
+/// Size of an encoded G1 point
+const G1_SIZE: usize = 64;
+/// Size of an encoded G2 point
+const G2_SIZE: usize = 128;
+/// Size of a G1 and G2 point pair for the pairing check
+const PAIR_SIZE: usize = G1_SIZE + G2_SIZE;
+
+/// Point addition on the alt_bn128 curve at address 0x06, EIP-196
+pub struct Bn256Add {
+    eip1108: bool,
+}
+
+/// Scalar multiplication on the alt_bn128 curve at address 0x07, EIP-196
+pub struct Bn256Mul {
+    eip1108: bool,
+}
+
+/// Pairing check on the alt_bn128 curve at address 0x08, EIP-197
+pub struct Bn256Pairing {
+    eip1108: bool,
+}
+
+impl Bn256Add {
+    /// Priced as in EIP-196, before Istanbul
+    pub const BYZANTIUM: Self = Bn256Add { eip1108: false };
+    /// Priced as in EIP-1108, from Istanbul
+    pub const ISTANBUL: Self = Bn256Add { eip1108: true };
+}
+
+impl Bn256Mul {
+    /// Priced as in EIP-196, before Istanbul
+    pub const BYZANTIUM: Self = Bn256Mul { eip1108: false };
+    /// Priced as in EIP-1108, from Istanbul
+    pub const ISTANBUL: Self = Bn256Mul { eip1108: true };
+}
+
+impl Bn256Pairing {
+    /// Priced as in EIP-197, before Istanbul
+    pub const BYZANTIUM: Self = Bn256Pairing { eip1108: false };
+    /// Priced as in EIP-1108, from Istanbul
+    pub const ISTANBUL: Self = Bn256Pairing { eip1108: true };
+}
+
+/// Reads a field element, failing if it is not below the field modulus
+fn read_fq(input: &[u8]) -> Result<Fq, EvmError> {
+    Fq::from_slice(input).map_err(|_| EvmError::PrecompileFailure)
+}
+
+/// Reads a G1 point, (0, 0) being the point at infinity
+fn read_g1(input: &[u8]) -> Result<G1, EvmError> {
+    let x = read_fq(&input[..32])?;
+    let y = read_fq(&input[32..64])?;
+    if x.is_zero() && y.is_zero() {
+        return Ok(G1::zero());
+    }
+    AffineG1::new(x, y)
+        .map(G1::from)
+        .map_err(|_| EvmError::PrecompileFailure)
+}
+
+/// Reads a G2 point, whose coordinates are encoded imaginary part first
+fn read_g2(input: &[u8]) -> Result<G2, EvmError> {
+    let x = Fq2::new(read_fq(&input[32..64])?, read_fq(&input[..32])?);
+    let y = Fq2::new(read_fq(&input[96..128])?, read_fq(&input[64..96])?);
+    if x.is_zero() && y.is_zero() {
+        return Ok(G2::zero());
+    }
+    AffineG2::new(x, y)
+        .map(G2::from)
+        .map_err(|_| EvmError::PrecompileFailure)
+}
+
+/// Encodes a G1 point, the point at infinity as (0, 0)
+fn write_g1(point: G1) -> Vec<u8> {
+    let mut output = vec![0u8; G1_SIZE];
+    if let Some(point) = AffineG1::from_jacobian(point) {
+        point.x().to_big_endian(&mut output[..32]).unwrap();
+        point.y().to_big_endian(&mut output[32..]).unwrap();
+    }
+    output
+}
+
+impl Precompile for Bn256Add {
+    fn gas_cost(&self, _input: &[u8]) -> GasCost {
+        if self.eip1108 {
+            GasCost::PRECOMPILE_BN256ADD
+        } else {
+            GasCost::PRECOMPILE_BN256ADD_BYZANTIUM
+        }
+    }
+
+    fn execute(&self, input: &[u8]) -> Result<Vec<u8>, EvmError> {
+        let input = right_pad::<{ 2 * G1_SIZE }>(input);
+        let a = read_g1(&input[..G1_SIZE])?;
+        let b = read_g1(&input[G1_SIZE..])?;
+        Ok(write_g1(a + b))
+    }
+}
+
+impl Precompile for Bn256Mul {
+    fn gas_cost(&self, _input: &[u8]) -> GasCost {
+        if self.eip1108 {
+            GasCost::PRECOMPILE_BN256MUL
+        } else {
+            GasCost::PRECOMPILE_BN256MUL_BYZANTIUM
+        }
+    }
+
+    fn execute(&self, input: &[u8]) -> Result<Vec<u8>, EvmError> {
+        let input = right_pad::<{ G1_SIZE + 32 }>(input);
+        let point = read_g1(&input[..G1_SIZE])?;
+        let scalar = Fr::from_slice(&input[G1_SIZE..]).map_err(|_| EvmError::PrecompileFailure)?;
+        Ok(write_g1(point * scalar))
+    }
+}
+
+impl Precompile for Bn256Pairing {
+    fn gas_cost(&self, input: &[u8]) -> GasCost {
+        let pairs = (input.len() / PAIR_SIZE) as u64;
+        let (base, per_pair) = if self.eip1108 {
+            (
+                GasCost::PRECOMPILE_BN256PAIRING,
+                GasCost::PRECOMPILE_BN256PAIRING_PER_PAIR,
+            )
+        } else {
+            (
+                GasCost::PRECOMPILE_BN256PAIRING_BYZANTIUM,
+                GasCost::PRECOMPILE_BN256PAIRING_PER_PAIR_BYZANTIUM,
+            )
+        };
+        base + GasCost::from(per_pair.as_u64() * pairs)
+    }
+
+    fn execute(&self, input: &[u8]) -> Result<Vec<u8>, EvmError> {
+        if !input.len().is_multiple_of(PAIR_SIZE) {
+            return Err(EvmError::PrecompileFailure);
+        }
+        let pairs = input
+            .chunks(PAIR_SIZE)
+            .map(|pair| Ok((read_g1(&pair[..G1_SIZE])?, read_g2(&pair[G1_SIZE..])?)))
+            .collect::<Result<Vec<_>, EvmError>>()?;
+
+        let mut output = vec![0u8; 32];
+        if bn::pairing_batch(&pairs) == Gt::one() {
+            output[31] = 1;
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::precompiles::tests::check_fixtures;
+
+    #[test]
+    fn test_bn256_add() {
+        check_fixtures(&Bn256Add::ISTANBUL, include_str!("testdata/bn256Add.json"));
+    }
+
+    #[test]
+    fn test_bn256_mul() {
+        check_fixtures(
+            &Bn256Mul::ISTANBUL,
+            include_str!("testdata/bn256ScalarMul.json"),
+        );
+    }
+
+    #[test]
+    fn test_bn256_pairing() {
+        check_fixtures(
+            &Bn256Pairing::ISTANBUL,
+            include_str!("testdata/bn256Pairing.json"),
+        );
+    }
+
+    #[test]
+    fn test_bn256_invalid_input() {
+        // (1, 3) is not on the curve
+        let mut input = [0u8; 128];
+        input[31] = 1;
+        input[63] = 3;
+        assert_eq!(
+            Bn256Add::ISTANBUL.execute(&input),
+            Err(EvmError::PrecompileFailure)
+        );
+        assert_eq!(
+            Bn256Mul::ISTANBUL.execute(&input),
+            Err(EvmError::PrecompileFailure)
+        );
+        // the pairing input must be a whole number of pairs
+        assert_eq!(
+            Bn256Pairing::ISTANBUL.execute(&input),
+            Err(EvmError::PrecompileFailure)
+        );
+    }
+
+    #[test]
+    fn test_bn256_byzantium_gas() {
+        assert_eq!(Bn256Add::BYZANTIUM.gas_cost(&[]), GasCost::from(500));
+        assert_eq!(Bn256Mul::BYZANTIUM.gas_cost(&[]), GasCost::from(40_000));
+        assert_eq!(
+            Bn256Pairing::BYZANTIUM.gas_cost(&[0; 2 * PAIR_SIZE]),
+            GasCost::from(260_000)
+        );
+        assert_eq!(
+            Bn256Pairing::ISTANBUL.gas_cost(&[0; 2 * PAIR_SIZE]),
+            GasCost::from(113_000)
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use crate::evm::precompiles::{right_pad, Precompile};
+use crate::evm::utils::keccak256;
+use crate::evm::{EvmError, GasCost};
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+/// Recovers the signer address of a secp256k1 signature, at address 0x01
+///
+/// The input is `hash`, `v`, `r` and `s`, 32 bytes each.
+/// Outputs the address left padded to 32 bytes, or nothing if the signature is invalid.
+pub struct EcRecover;
+
+impl Precompile for EcRecover {
+    fn gas_cost(&self, _input: &[u8]) -> GasCost {
+        GasCost::PRECOMPILE_ECRECOVER_BASE
+    }
+
+    fn execute(&self, input: &[u8]) -> Result<Vec<u8>, EvmError> {
+        let input = right_pad::<128>(input);
+        // v must be a 32-byte word equal to 27 or 28
+        if input[32..63].iter().any(|&byte| byte != 0) || !matches!(input[63], 27 | 28) {
+            return Ok(Vec::new());
+        }
+        Ok(recover(&input[..32], &input[64..], input[63] - 27).unwrap_or_default())
+    }
+}
+
+/// Returns the signer address of `hash`, left padded to 32 bytes
+fn recover(hash: &[u8], signature: &[u8], recovery_id: u8) -> Option<Vec<u8>> {
+    let mut signature = Signature::from_slice(signature).ok()?;
+    let mut recovery_id = recovery_id;
+    // unlike transactions, the precompile accepts high s values
+    if let Some(normalized) = signature.normalize_s() {
+        signature = normalized;
+        recovery_id ^= 1;
+    }
+    let recovery_id = RecoveryId::from_byte(recovery_id)?;
+    let key = VerifyingKey::recover_from_prehash(hash, &signature, recovery_id).ok()?;
+    let mut output = keccak256(&key.to_encoded_point(false).as_bytes()[1..]).to_vec();
+    output[..12].fill(0);
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::precompiles::tests::check_fixtures;
+
+    #[test]
+    fn test_ecrecover() {
+        check_fixtures(&EcRecover, include_str!("testdata/ecRecover.json"));
+    }
+}
@@ -0,0 +1,67 @@
+use crate::evm::precompiles::Precompile;
+use crate::evm::{EvmError, GasCost};
+use ripemd::Digest;
+
+/// SHA2-256 hash at address 0x02
+pub struct Sha256;
+
+impl Precompile for Sha256 {
+    fn gas_cost(&self, input: &[u8]) -> GasCost {
+        GasCost::PRECOMPILE_SHA256_BASE + GasCost::PRECOMPILE_SHA256_PER_WORD.per_word(input.len())
+    }
+
+    fn execute(&self, input: &[u8]) -> Result<Vec<u8>, EvmError> {
+        Ok(sha2::Sha256::digest(input).to_vec())
+    }
+}
+
+/// RIPEMD-160 hash at address 0x03, left padded to 32 bytes
+pub struct Ripemd160;
+
+impl Precompile for Ripemd160 {
+    fn gas_cost(&self, input: &[u8]) -> GasCost {
+        GasCost::PRECOMPILE_RIPEMD160_BASE
+            + GasCost::PRECOMPILE_RIPEMD160_PER_WORD.per_word(input.len())
+    }
+
+    fn execute(&self, input: &[u8]) -> Result<Vec<u8>, EvmError> {
+        let mut output = vec![0u8; 12];
+        output.extend_from_slice(&ripemd::Ripemd160::digest(input));
+        Ok(output)
+    }
+}
+
+/// Returns the input as is, at address 0x04
+pub struct Identity;
+
+impl Precompile for Identity {
+    fn gas_cost(&self, input: &[u8]) -> GasCost {
+        GasCost::PRECOMPILE_IDENTITY_BASE
+            + GasCost::PRECOMPILE_IDENTITY_PER_WORD.per_word(input.len())
+    }
+
+    fn execute(&self, input: &[u8]) -> Result<Vec<u8>, EvmError> {
+        Ok(input.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::precompiles::tests::check_fixtures;
+
+    #[test]
+    fn test_sha256() {
+        check_fixtures(&Sha256, include_str!("testdata/sha256.json"));
+    }
+
+    #[test]
+    fn test_ripemd160() {
+        check_fixtures(&Ripemd160, include_str!("testdata/ripemd160.json"));
+    }
+
+    #[test]
+    fn test_identity() {
+        check_fixtures(&Identity, include_str!("testdata/identity.json"));
+    }
+}
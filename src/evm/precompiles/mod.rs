@@ -0,0 +1,227 @@
+mod blake2f;
+mod bn256;
+mod ecrecover;
+mod hash;
+mod modexp;
+
+use crate::evm::utils::word_to_address;
+use crate::evm::{
+    Address, Evm, EvmError, ExecutionError, GasCategory, GasCost, HaltReason, OpcodeId, SpecId,
+};
+pub use blake2f::Blake2F;
+pub use bn256::{Bn256Add, Bn256Mul, Bn256Pairing};
+pub use ecrecover::EcRecover;
+pub use hash::{Identity, Ripemd160, Sha256};
+pub use modexp::ModExp;
+use primitive_types::U256;
+
+/// Result of a successful precompile call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrecompileOutput {
+    pub gas_used: GasCost,
+    pub output: Vec<u8>,
+}
+
+/// A contract implemented natively at a fixed address
+pub trait Precompile {
+    /// Returns the gas cost of running with `input`
+    fn gas_cost(&self, input: &[u8]) -> GasCost;
+
+    /// Computes the output for `input`, without charging gas
+    ///
+    /// # Returns
+    ///
+    /// Returns the output, or `PrecompileFailure` error on malformed input.
+    fn execute(&self, input: &[u8]) -> Result<Vec<u8>, EvmError>;
+
+    /// Runs with `input` and at most `gas_limit` gas
+    ///
+    /// # Returns
+    ///
+    /// Returns the output and the gas used.
+    /// When `gas_limit` is not enough, returns `OutOfGas` error.
+    /// On malformed input, returns `PrecompileFailure` error.
+    fn run(&self, input: &[u8], gas_limit: GasCost) -> Result<PrecompileOutput, EvmError> {
+        let gas_used = self.gas_cost(input);
+        if gas_used > gas_limit {
+            return Err(EvmError::out_of_gas(gas_used, gas_limit));
+        }
+        let output = self.execute(input)?;
+        Ok(PrecompileOutput { gas_used, output })
+    }
+}
+
+/// Returns the addresses of the precompiles under the rules of `spec`
+pub fn precompile_addresses(spec: SpecId) -> impl Iterator<Item = Address> {
+    let last = if spec.is_enabled_in(SpecId::Istanbul) {
+        0x09
+    } else if spec.is_enabled_in(SpecId::Byzantium) {
+        0x08
+    } else {
+        0x04
+    };
+    (0x01..=last).map(|n: u64| word_to_address(U256::from(n)))
+}
+
+/// Returns the precompile at `address` under the rules of `spec`, if any
+pub fn precompile(address: Address, spec: SpecId) -> Option<&'static dyn Precompile> {
+    let bytes = address.as_bytes();
+    if bytes[..19].iter().any(|&byte| byte != 0) {
+        return None;
+    }
+    let byzantium = spec.is_enabled_in(SpecId::Byzantium);
+    let istanbul = spec.is_enabled_in(SpecId::Istanbul);
+    match bytes[19] {
+        0x01 => Some(&EcRecover),
+        0x02 => Some(&Sha256),
+        0x03 => Some(&Ripemd160),
+        0x04 => Some(&Identity),
+        0x05 if spec.is_enabled_in(SpecId::Berlin) => Some(&ModExp::BERLIN),
+        0x05 if byzantium => Some(&ModExp::BYZANTIUM),
+        0x06 if istanbul => Some(&Bn256Add::ISTANBUL),
+        0x06 if byzantium => Some(&Bn256Add::BYZANTIUM),
+        0x07 if istanbul => Some(&Bn256Mul::ISTANBUL),
+        0x07 if byzantium => Some(&Bn256Mul::BYZANTIUM),
+        0x08 if istanbul => Some(&Bn256Pairing::ISTANBUL),
+        0x08 if byzantium => Some(&Bn256Pairing::BYZANTIUM),
+        0x09 if istanbul => Some(&Blake2F),
+        _ => None,
+    }
+}
+
+impl Evm {
+    /// Runs the precompile at `address` under the rules of the evm's spec,
+    /// with `input` and at most `gas_limit` gas, outside of any call frame.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if there is no precompile at `address`, or the result of the run.
+    pub fn call_precompile(
+        &self,
+        address: Address,
+        input: &[u8],
+        gas_limit: impl Into<GasCost>,
+    ) -> Option<Result<PrecompileOutput, EvmError>> {
+        precompile(address, self.spec).map(|precompile| precompile.run(input, gas_limit.into()))
+    }
+
+    /// Runs `precompile` as the top level frame, with the calldata as input
+    /// and all the gas left, e.g. for a transaction sent to a precompile.
+    ///
+    /// Halts with `RETURN` and the output, or exceptionally when the run fails.
+    pub(crate) fn run_precompile(&mut self, precompile: &dyn Precompile) {
+        match precompile.run(&self.calldata, self.gas.left()) {
+            Ok(PrecompileOutput { gas_used, output }) => {
+                // the run fails when the gas left is not enough
+                self.gas.charge([(GasCategory::Dynamic, gas_used)]).unwrap();
+                self.return_data = output;
+                self.halt_reason = Some(HaltReason::Return);
+            }
+            // there is no code, the error is at its end
+            Err(error) => self.halt_with(ExecutionError {
+                pc: self.program_counter,
+                opcode: OpcodeId::STOP,
+                error,
+            }),
+        }
+    }
+}
+
+/// Returns `input` right padded with zeros, or truncated, to `N` bytes
+fn right_pad<const N: usize>(input: &[u8]) -> [u8; N] {
+    let mut padded = [0u8; N];
+    let len = input.len().min(N);
+    padded[..len].copy_from_slice(&input[..len]);
+    padded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::utils::address;
+    use crate::evm::Env;
+
+    /// Runs `precompile` against test vectors in the go-ethereum format,
+    /// checking the output and the gas used.
+    pub(super) fn check_fixtures(precompile: &dyn Precompile, fixtures: &str) {
+        let cases: serde_json::Value = serde_json::from_str(fixtures).unwrap();
+        for case in cases.as_array().unwrap() {
+            let name = case["Name"].as_str().unwrap();
+            let input = hex::decode(case["Input"].as_str().unwrap()).unwrap();
+            if let Some(error) = case.get("ExpectedError") {
+                assert_eq!(
+                    precompile.run(&input, GasCost::from(u64::MAX)),
+                    Err(EvmError::PrecompileFailure),
+                    "{}: {}",
+                    name,
+                    error
+                );
+                continue;
+            }
+            let expected = hex::decode(case["Expected"].as_str().unwrap()).unwrap();
+            let gas = GasCost::from(case["Gas"].as_u64().unwrap());
+            assert_eq!(
+                precompile.run(&input, gas),
+                Ok(PrecompileOutput {
+                    gas_used: gas,
+                    output: expected
+                }),
+                "{}",
+                name
+            );
+            if gas > GasCost::ZERO {
+                assert_eq!(
+                    precompile.run(&input, gas - 1),
+                    Err(EvmError::out_of_gas(gas, gas - 1)),
+                    "{}",
+                    name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_precompile_addresses() {
+        let evm = Evm::new(Env::default(), 0);
+        for n in [0u64, 10, 0x100] {
+            let address = address(n);
+            assert!(precompile(address, SpecId::Cancun).is_none());
+            assert!(evm.call_precompile(address, &[], 1000).is_none());
+        }
+        // the identity precompile
+        let address = address(4);
+        assert_eq!(
+            evm.call_precompile(address, &[1, 2, 3], 1000),
+            Some(Ok(PrecompileOutput {
+                gas_used: GasCost::from(18),
+                output: vec![1, 2, 3]
+            }))
+        );
+        assert_eq!(
+            evm.call_precompile(address, &[1, 2, 3], 17),
+            Some(Err(EvmError::out_of_gas(18, 17)))
+        );
+    }
+
+    #[test]
+    fn test_precompiles_by_fork() {
+        let exists = |n: u64, spec: SpecId| {
+            let address = address(n);
+            let found = precompile(address, spec).is_some();
+            assert_eq!(precompile_addresses(spec).any(|a| a == address), found);
+            found
+        };
+        assert!(exists(4, SpecId::Frontier));
+        assert!(!exists(5, SpecId::Frontier));
+        assert!(!exists(8, SpecId::SpuriousDragon));
+        assert!(exists(8, SpecId::Byzantium));
+        assert!(!exists(9, SpecId::Petersburg));
+        assert!(exists(9, SpecId::Istanbul));
+
+        // priced by the fork
+        let bn256_add = address(6);
+        let gas_cost = |spec| precompile(bn256_add, spec).unwrap().gas_cost(&[]);
+        assert_eq!(gas_cost(SpecId::Byzantium), GasCost::from(500));
+        assert_eq!(gas_cost(SpecId::Istanbul), GasCost::from(150));
+    }
+}
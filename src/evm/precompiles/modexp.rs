@@ -0,0 +1,217 @@
+use crate::evm::precompiles::Precompile;
+use crate::evm::utils::copy_padded;
+use crate::evm::{EvmError, GasCost};
+use num_bigint::BigUint;
+use primitive_types::U256;
+
+/// Modular exponentiation at address 0x05, EIP-198
+///
+/// The input is the lengths of base, exponent and modulus, 32 bytes each,
+/// followed by the base, exponent and modulus themselves.
+pub struct ModExp {
+    eip2565: bool,
+}
+
+impl ModExp {
+    /// Priced as in EIP-198, before Berlin
+    pub const BYZANTIUM: Self = ModExp { eip2565: false };
+    /// Priced as in EIP-2565, from Berlin
+    pub const BERLIN: Self = ModExp { eip2565: true };
+}
+
+/// Divisor of the gas cost before Berlin, EIP-198
+const GQUADDIVISOR_BYZANTIUM: u128 = 20;
+/// Divisor of the gas cost, EIP-2565
+const GQUADDIVISOR: u128 = 3;
+
+/// Lengths of the base, exponent and modulus
+struct Lengths {
+    base: U256,
+    exp: U256,
+    modulus: U256,
+}
+
+impl Lengths {
+    fn read(input: &[u8]) -> Self {
+        Lengths {
+            base: read_word(input, 0),
+            exp: read_word(input, 32),
+            modulus: read_word(input, 64),
+        }
+    }
+}
+
+/// Returns the 32 bytes of `input` at `offset` as a word, zero padded
+fn read_word(input: &[u8], offset: usize) -> U256 {
+    U256::from_big_endian(&read_padded(input, U256::from(offset), 32))
+}
+
+/// Returns `len` bytes of `input` at `offset`, zero padded
+fn read_padded(input: &[u8], offset: U256, len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    copy_padded(&mut bytes, input, offset);
+    bytes
+}
+
+/// Returns `value` as u64, or `u64::MAX` if it does not fit
+fn saturating_u64(value: U256) -> u64 {
+    if value > U256::from(u64::MAX) {
+        u64::MAX
+    } else {
+        value.as_u64()
+    }
+}
+
+/// Returns the number of bits of `value`
+fn bit_len(value: &[u8]) -> u64 {
+    match value.iter().position(|&byte| byte != 0) {
+        Some(index) => (value.len() - index) as u64 * 8 - value[index].leading_zeros() as u64,
+        None => 0,
+    }
+}
+
+/// Returns the multiplication complexity of EIP-198 for operands of `len` bytes
+fn complexity_byzantium(len: u128) -> u128 {
+    let square = len.saturating_mul(len);
+    if len <= 64 {
+        square
+    } else if len <= 1024 {
+        (square / 4 + 96 * len).saturating_sub(3072)
+    } else {
+        (square / 16 + 480 * len).saturating_sub(199_680)
+    }
+}
+
+impl Precompile for ModExp {
+    fn gas_cost(&self, input: &[u8]) -> GasCost {
+        let lengths = Lengths::read(input);
+        let exp_len = saturating_u64(lengths.exp);
+        let max_len = saturating_u64(lengths.base.max(lengths.modulus)) as u128;
+
+        let complexity = if self.eip2565 {
+            let words = max_len.div_ceil(8);
+            words.saturating_mul(words)
+        } else {
+            complexity_byzantium(max_len)
+        };
+
+        // only the first 32 bytes of the exponent count
+        let exp_head = read_padded(
+            input,
+            U256::from(96).saturating_add(lengths.base),
+            exp_len.min(32) as usize,
+        );
+        let head_bits = bit_len(&exp_head).saturating_sub(1);
+        let iterations = if exp_len <= 32 {
+            head_bits
+        } else {
+            (exp_len - 32).saturating_mul(8).saturating_add(head_bits)
+        };
+
+        let divisor = if self.eip2565 {
+            GQUADDIVISOR
+        } else {
+            GQUADDIVISOR_BYZANTIUM
+        };
+        let gas = complexity.saturating_mul(iterations.max(1) as u128) / divisor;
+        let gas = GasCost::from(gas.min(u64::MAX as u128) as u64);
+        if self.eip2565 {
+            GasCost::PRECOMPILE_MODEXP + gas.max(GasCost::PRECOMPILE_MODEXP_MIN)
+        } else {
+            GasCost::PRECOMPILE_MODEXP + gas
+        }
+    }
+
+    fn execute(&self, input: &[u8]) -> Result<Vec<u8>, EvmError> {
+        let lengths = Lengths::read(input);
+        if lengths.base.is_zero() && lengths.modulus.is_zero() {
+            return Ok(Vec::new());
+        }
+        // lengths this large can never be paid for
+        let limit = U256::from(u32::MAX);
+        if lengths.base > limit || lengths.exp > limit || lengths.modulus > limit {
+            return Err(EvmError::PrecompileFailure);
+        }
+        let (base_len, exp_len, mod_len) = (
+            lengths.base.as_usize(),
+            lengths.exp.as_usize(),
+            lengths.modulus.as_usize(),
+        );
+
+        let base = read_padded(input, U256::from(96), base_len);
+        let exp_offset = U256::from(96) + lengths.base;
+        let exp = read_padded(input, exp_offset, exp_len);
+        let modulus = read_padded(input, exp_offset + lengths.exp, mod_len);
+
+        let modulus = BigUint::from_bytes_be(&modulus);
+        let result = if modulus == BigUint::from(0u8) {
+            Vec::new()
+        } else {
+            BigUint::from_bytes_be(&base)
+                .modpow(&BigUint::from_bytes_be(&exp), &modulus)
+                .to_bytes_be()
+        };
+
+        // left pad the result to the length of the modulus
+        let mut output = vec![0u8; mod_len];
+        output[mod_len - result.len()..].copy_from_slice(&result);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::precompiles::tests::check_fixtures;
+
+    #[test]
+    fn test_modexp() {
+        check_fixtures(
+            &ModExp::BERLIN,
+            include_str!("testdata/modexp_eip2565.json"),
+        );
+    }
+
+    #[test]
+    fn test_modexp_huge_lengths() {
+        // huge exponent length with empty base and modulus
+        let mut input = vec![0u8; 96];
+        input[32..64].fill(0xff);
+        assert_eq!(
+            ModExp::BERLIN.gas_cost(&input),
+            GasCost::PRECOMPILE_MODEXP_MIN
+        );
+        assert_eq!(ModExp::BERLIN.execute(&input), Ok(Vec::new()));
+
+        // huge modulus length
+        input[64..96].fill(0xff);
+        assert_eq!(ModExp::BERLIN.gas_cost(&input), GasCost::from(u64::MAX));
+        assert_eq!(
+            ModExp::BERLIN.run(&input, GasCost::from(u64::MAX - 1)),
+            Err(EvmError::out_of_gas(u64::MAX, u64::MAX - 1))
+        );
+    }
+
+    #[test]
+    fn test_modexp_byzantium_gas() {
+        // example 1 of EIP-198: 3 ** (2 ** 256 - 2 ** 32 - 978) % (2 ** 256 - 2 ** 32 - 977)
+        let input = hex::decode(concat!(
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "03",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2e",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+        ))
+        .unwrap();
+        assert_eq!(ModExp::BYZANTIUM.gas_cost(&input), GasCost::from(13_056));
+        assert_eq!(ModExp::BERLIN.gas_cost(&input), GasCost::from(1360));
+        // no minimum before Berlin, and the complexity grows faster for long operands
+        assert_eq!(ModExp::BYZANTIUM.gas_cost(&[]), GasCost::ZERO);
+        assert_eq!(complexity_byzantium(100), 10_000 / 4 + 9600 - 3072);
+        assert_eq!(
+            complexity_byzantium(2048),
+            2048 * 2048 / 16 + 480 * 2048 - 199_680
+        );
+    }
+}
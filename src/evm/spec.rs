@@ -0,0 +1,49 @@
+use strum::EnumIter;
+
+/// Ethereum hard forks, in activation order
+///
+/// The fork decides which opcodes exist and which gas rules apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, EnumIter)]
+pub enum SpecId {
+    Frontier,
+    Homestead,
+    /// EIP-150 repricing
+    Tangerine,
+    /// EIP-158 state clearing
+    SpuriousDragon,
+    Byzantium,
+    /// Activated together with Petersburg, without EIP-1283
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    Merge,
+    Shanghai,
+    #[default]
+    Cancun,
+}
+
+impl SpecId {
+    /// Returns `true` if the rules of `fork` apply, i.e. `fork` is `self` or an earlier fork.
+    pub const fn is_enabled_in(self, fork: SpecId) -> bool {
+        self as u8 >= fork as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn test_is_enabled_in() {
+        let forks: Vec<_> = SpecId::iter().collect();
+        for (i, spec) in forks.iter().enumerate() {
+            for (j, fork) in forks.iter().enumerate() {
+                assert_eq!(spec.is_enabled_in(*fork), i >= j);
+            }
+        }
+        assert_eq!(SpecId::default(), SpecId::Cancun);
+    }
+}
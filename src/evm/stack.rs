@@ -32,7 +32,10 @@ impl Stack {
 
     /// pop a value from the stack
     pub fn try_pop(&mut self) -> Result<U256, EvmError> {
-        self.inner.pop().ok_or(EvmError::StackUnderflow)
+        self.inner.pop().ok_or(EvmError::StackUnderflow {
+            required: 1,
+            available: 0,
+        })
     }
 
     /// get the length of the stack
@@ -50,7 +53,10 @@ impl Stack {
         if self.inner.len() >= n {
             Ok(())
         } else {
-            Err(EvmError::StackUnderflow)
+            Err(EvmError::StackUnderflow {
+                required: n,
+                available: self.inner.len(),
+            })
         }
     }
 
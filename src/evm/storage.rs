@@ -1,9 +1,14 @@
-use crate::evm::{AccessList, EvmError, Gas, GasCost, OpcodeId, Stack};
+use crate::evm::{
+    AccessList, Address, EvmError, Gas, GasCategory, GasCost, OpcodeId, SpecId, Stack,
+};
 use primitive_types::U256;
 use std::collections::HashMap;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Storage {
     inner: HashMap<U256, U256>,
+    /// Values before the current transaction of the slots it wrote
+    original: HashMap<U256, U256>,
 }
 
 impl Default for Storage {
@@ -16,6 +21,7 @@ impl Storage {
     pub fn new() -> Self {
         Storage {
             inner: HashMap::new(),
+            original: HashMap::new(),
         }
     }
 
@@ -23,15 +29,40 @@ impl Storage {
         self.inner.get(&key).copied().unwrap_or_default()
     }
 
+    /// Set a slot without recording its original value,
+    /// so the value counts as set before the current transaction.
+    /// Setting zero empties the slot.
     pub fn raw_set(&mut self, key: U256, value: U256) -> U256 {
-        self.inner.insert(key, value).unwrap_or_default()
+        if value.is_zero() {
+            self.inner.remove(&key).unwrap_or_default()
+        } else {
+            self.inner.insert(key, value).unwrap_or_default()
+        }
+    }
+
+    /// Returns the value of the slot before the current transaction
+    pub fn original_value(&self, key: U256) -> U256 {
+        match self.original.get(&key) {
+            Some(value) => *value,
+            None => self.raw_get(key),
+        }
+    }
+
+    /// Make the current values the original ones, called between transactions
+    pub fn commit(&mut self) {
+        self.original.clear();
     }
 
     pub fn raw_clear(&mut self, key: U256) {
         self.inner.remove(&key);
     }
 
-    /// Implementation of the SLOAD opcode
+    /// Iterate over the non-empty slots, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&U256, &U256)> {
+        self.inner.iter()
+    }
+
+    /// Implementation of the SLOAD opcode, reading the storage of `address`
     ///
     /// # Returns
     ///
@@ -39,43 +70,58 @@ impl Storage {
     /// When gas is not enough, returns `OutOfGas` error.
     /// When the stack is empty, returns `StackUnderflow` error.
     pub fn sload(
-        &mut self,
+        &self,
+        spec: SpecId,
+        address: Address,
         access_list: &mut AccessList,
         gas: &mut Gas,
         stack: &mut Stack,
     ) -> Result<U256, EvmError> {
-        let static_gas = OpcodeId::SLOAD.constant_gas_cost();
+        let static_gas = OpcodeId::SLOAD.constant_gas_cost_in(spec);
 
         // key: storage slot to be read.
         let key = stack.try_pop()?;
 
-        let dynamic_gas = if access_list.is_warm_slot(key) {
-            GasCost::WARM_ACCESS
+        let (warm_gas, cold_gas) = if !spec.is_enabled_in(SpecId::Berlin) {
+            (GasCost::ZERO, GasCost::ZERO)
+        } else if access_list.is_warm_slot(address, key) {
+            (GasCost::WARM_ACCESS, GasCost::ZERO)
         } else {
-            GasCost::COLD_SLOAD
+            // a cold read costs `COLD_SLOAD` instead of `WARM_ACCESS`
+            (
+                GasCost::WARM_ACCESS,
+                GasCost::COLD_SLOAD - GasCost::WARM_ACCESS,
+            )
         };
-        let total_gas = static_gas + dynamic_gas;
-        gas.use_gas(total_gas)?;
-        access_list.add_warm_slot(key);
+        gas.charge([
+            (GasCategory::Base, static_gas + warm_gas),
+            (GasCategory::ColdAccess, cold_gas),
+        ])?;
+        access_list.add_warm_slot(address, key);
         let value = self.raw_get(key);
         stack.try_push(value).unwrap(); // impossible to fail, so unwrap
 
         Ok(value)
     }
 
-    /// Implementation of the SSTORE opcode
+    /// Implementation of the SSTORE opcode, writing the storage of `address`
+    ///
+    /// Charges and refunds gas by the net gas metering of EIP-2200 from Istanbul,
+    /// with the cold access of EIP-2929 and the reduced refunds of EIP-3529 on top.
     ///
     /// # Returns
     ///
-    /// Returns the original value of the storage slot, or zero if the slot was empty.
-    /// When gas is not enough, returns `OutOfGas` error.
+    /// Returns the storage slot and its previous value, zero if the slot was empty.
+    /// When gas is not enough, or not more than `SSTORE_SENTRY` is left, returns `OutOfGas` error.
     /// When the stack has less than 2 elements, returns `StackUnderflow` error.
     pub fn sstore(
         &mut self,
+        spec: SpecId,
+        address: Address,
         access_list: &mut AccessList,
         gas: &mut Gas,
         stack: &mut Stack,
-    ) -> Result<U256, EvmError> {
+    ) -> Result<(U256, U256), EvmError> {
         let static_gas = OpcodeId::SSTORE.constant_gas_cost();
 
         // key: storage slot to be modified.
@@ -83,35 +129,326 @@ impl Storage {
         // value: value to be stored in the storage slot.
         let value = stack.try_pop()?;
 
-        // current value of the storage slot.
+        // EIP-2200 reentrancy sentry, a call with only the stipend cannot write
+        if spec.is_enabled_in(SpecId::Istanbul) && gas.left() <= GasCost::SSTORE_SENTRY {
+            return Err(EvmError::out_of_gas(GasCost::SSTORE_SENTRY + 1, gas.left()));
+        }
+
         let current_value = self.raw_get(key);
-        // original_value: value of the storage slot before the current transaction.
-        // in this game, original value is always zero
-        // if value == current_value
-        //     if key is warm
-        //         base_dynamic_gas = GasCost::WARM_ACCESS
-        //     else
-        //         base_dynamic_gas = GasCost::WARM_ACCESS
-        // else if current_value == original_value // aka. current_value == 0
-        //     if original_value == 0       // always true
-        //         base_dynamic_gas = GasCost::SSTORE_SET
-        //     else
-        //         base_dynamic_gas = GasCost::SSTORE_RESET
-        // else
-        //     base_dynamic_gas = GasCost::WARM_ACCESS
-        let base_dynamic_gas = if current_value.is_zero() {
+        let original_value = self.original_value(key);
+        let (dynamic_gas, refund) = sstore_cost(spec, original_value, current_value, value);
+        // EIP-2929 charges the first access of the slot on top
+        let cold_gas =
+            if spec.is_enabled_in(SpecId::Berlin) && !access_list.is_warm_slot(address, key) {
+                GasCost::COLD_SLOAD
+            } else {
+                GasCost::ZERO
+            };
+        gas.charge([
+            (GasCategory::Base, static_gas),
+            (GasCategory::StorageWrite, dynamic_gas),
+            (GasCategory::ColdAccess, cold_gas),
+        ])?;
+        gas.record_refund(refund);
+        access_list.add_warm_slot(address, key);
+        self.original.entry(key).or_insert(original_value);
+        Ok((key, self.raw_set(key, value)))
+    }
+}
+
+/// Returns the gas cost of a warm SSTORE and the refund it earns,
+/// negative when it takes back an earlier refund.
+fn sstore_cost(spec: SpecId, original: U256, current: U256, new: U256) -> (GasCost, i64) {
+    let clear_refund = if spec.is_enabled_in(SpecId::London) {
+        GasCost::SSTORE_CLEARS_SCHEDULE
+    } else {
+        GasCost::SSTORE_CLEARS_SCHEDULE_FRONTIER
+    }
+    .as_u64() as i64;
+
+    if !spec.is_enabled_in(SpecId::Istanbul) {
+        // before EIP-2200, only the current value matters
+        return if current.is_zero() && !new.is_zero() {
+            (GasCost::SSTORE_SET, 0)
+        } else if !current.is_zero() && new.is_zero() {
+            (GasCost::SSTORE_RESET_FRONTIER, clear_refund)
+        } else {
+            (GasCost::SSTORE_RESET_FRONTIER, 0)
+        };
+    }
+
+    let (sload_gas, reset_gas) = if spec.is_enabled_in(SpecId::Berlin) {
+        (GasCost::WARM_ACCESS, GasCost::SSTORE_RESET)
+    } else {
+        (GasCost::SLOAD_ISTANBUL, GasCost::SSTORE_RESET_FRONTIER)
+    };
+
+    if new == current {
+        // no-op
+        return (sload_gas, 0);
+    }
+    if original == current {
+        // first write of the slot in the transaction
+        return if original.is_zero() {
+            (GasCost::SSTORE_SET, 0)
+        } else if new.is_zero() {
+            (reset_gas, clear_refund)
+        } else {
+            (reset_gas, 0)
+        };
+    }
+
+    // the slot is dirty, the first write already paid for it
+    let mut refund = 0;
+    if !original.is_zero() {
+        if current.is_zero() {
+            // the clear is undone
+            refund -= clear_refund;
+        } else if new.is_zero() {
+            refund += clear_refund;
+        }
+    }
+    if new == original {
+        // back to the original value, refund the first write down to a no-op
+        let first_write = if original.is_zero() {
             GasCost::SSTORE_SET
         } else {
-            GasCost::WARM_ACCESS
+            reset_gas
         };
-        let dynamic_gas = if access_list.is_warm_slot(key) {
-            base_dynamic_gas
+        refund += (first_write.as_u64() - sload_gas.as_u64()) as i64;
+    }
+    (sload_gas, refund)
+}
+
+/// Transient storage (EIP-1153), which behaves like storage
+/// but is cleared at the end of every transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransientStorage {
+    inner: HashMap<U256, U256>,
+}
+
+impl TransientStorage {
+    pub fn new() -> Self {
+        TransientStorage {
+            inner: HashMap::new(),
+        }
+    }
+
+    pub fn raw_get(&self, key: U256) -> U256 {
+        self.inner.get(&key).copied().unwrap_or_default()
+    }
+
+    pub fn raw_set(&mut self, key: U256, value: U256) -> U256 {
+        if value.is_zero() {
+            self.inner.remove(&key).unwrap_or_default()
         } else {
-            base_dynamic_gas + GasCost::COLD_SLOAD
+            self.inner.insert(key, value).unwrap_or_default()
+        }
+    }
+
+    /// Clear all the slots, called between transactions
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// Iterate over the non-empty slots, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&U256, &U256)> {
+        self.inner.iter()
+    }
+
+    /// Implementation of the TLOAD opcode
+    ///
+    /// # Returns
+    ///
+    /// Returns the value of the transient slot, or zero if the slot is empty.
+    /// When gas is not enough, returns `OutOfGas` error.
+    /// When the stack is empty, returns `StackUnderflow` error.
+    pub fn tload(&self, gas: &mut Gas, stack: &mut Stack) -> Result<U256, EvmError> {
+        gas.use_gas(OpcodeId::TLOAD.constant_gas_cost())?;
+
+        // key: transient slot to be read.
+        let key = stack.try_pop()?;
+        let value = self.raw_get(key);
+        stack.try_push(value).unwrap(); // impossible to fail, so unwrap
+
+        Ok(value)
+    }
+
+    /// Implementation of the TSTORE opcode
+    ///
+    /// # Returns
+    ///
+    /// Returns the transient slot and its previous value.
+    /// When gas is not enough, returns `OutOfGas` error.
+    /// When the stack has less than 2 elements, returns `StackUnderflow` error.
+    pub fn tstore(&mut self, gas: &mut Gas, stack: &mut Stack) -> Result<(U256, U256), EvmError> {
+        gas.use_gas(OpcodeId::TSTORE.constant_gas_cost())?;
+
+        // key: transient slot to be modified.
+        let key = stack.try_pop()?;
+        // value: value to be stored in the transient slot.
+        let value = stack.try_pop()?;
+
+        Ok((key, self.raw_set(key, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::{Bytecode, Env, Evm, HaltReason};
+
+    /// Runs `code` with slot 0 warm and set to `original` before the transaction,
+    /// returning the gas used and the refund
+    fn run_sstore(spec: SpecId, code: &str, original: u64) -> (u64, i64) {
+        let mut evm = Evm::new(Env::default(), 100_000).with_spec(spec);
+        evm.bytecode = Bytecode::from(&hex::decode(code).unwrap()[..]);
+        evm.access_list
+            .add_warm_slot(evm.context.address, U256::zero());
+        evm.world
+            .account_mut(evm.context.address)
+            .storage
+            .raw_set(U256::zero(), U256::from(original));
+        let outcome = evm.run(100);
+        assert_eq!(outcome.halt_reason, HaltReason::Stop);
+        (outcome.gas_used.as_u64(), evm.gas.refunded())
+    }
+
+    #[test]
+    fn test_sstore_eip2200() {
+        // test cases of EIP-2200: code, gas used, refund, original value
+        for (code, gas_used, refund, original) in [
+            ("60006000556000600055", 1612, 0, 0),
+            ("60006000556001600055", 20812, 0, 0),
+            ("60016000556000600055", 20812, 19200, 0),
+            ("60016000556002600055", 20812, 0, 0),
+            ("60016000556001600055", 20812, 0, 0),
+            ("60006000556000600055", 5812, 15000, 1),
+            ("60006000556001600055", 5812, 4200, 1),
+            ("60006000556002600055", 5812, 0, 1),
+            ("60026000556000600055", 5812, 15000, 1),
+            ("60026000556003600055", 5812, 0, 1),
+            ("60026000556001600055", 5812, 4200, 1),
+            ("60026000556002600055", 5812, 0, 1),
+            ("60016000556000600055", 5812, 15000, 1),
+            ("60016000556002600055", 5812, 0, 1),
+            ("60016000556001600055", 1612, 0, 1),
+            ("600160005560006000556001600055", 40818, 19200, 0),
+            ("600060005560016000556000600055", 10818, 19200, 1),
+        ] {
+            assert_eq!(
+                run_sstore(SpecId::Istanbul, code, original),
+                (gas_used, refund),
+                "{code} with original {original}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sstore_eip3529() {
+        // test cases of EIP-3529: code, gas used, refund, original value
+        for (code, gas_used, refund, original) in [
+            ("60006000556000600055", 212, 0, 0),
+            ("60006000556001600055", 20112, 0, 0),
+            ("60016000556000600055", 20112, 19900, 0),
+            ("60016000556002600055", 20112, 0, 0),
+            ("60016000556001600055", 20112, 0, 0),
+            ("60006000556000600055", 3012, 4800, 1),
+            ("60006000556001600055", 3012, 2800, 1),
+            ("60006000556002600055", 3012, 0, 1),
+            ("60026000556000600055", 3012, 4800, 1),
+            ("60026000556003600055", 3012, 0, 1),
+            ("60026000556001600055", 3012, 2800, 1),
+            ("60026000556002600055", 3012, 0, 1),
+            ("60016000556000600055", 3012, 4800, 1),
+            ("60016000556002600055", 3012, 0, 1),
+            ("60016000556001600055", 212, 0, 1),
+            ("600160005560006000556001600055", 40118, 19900, 0),
+            ("600060005560016000556000600055", 5918, 7600, 1),
+        ] {
+            assert_eq!(
+                run_sstore(SpecId::London, code, original),
+                (gas_used, refund),
+                "{code} with original {original}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sstore_original_value() {
+        let mut storage = Storage::new();
+        storage.raw_set(U256::zero(), U256::one());
+        let mut gas = Gas::new(100_000);
+        let mut stack = Stack::default();
+        for value in [2u64, 3] {
+            stack.try_push(U256::from(value)).unwrap();
+            stack.try_push(U256::zero()).unwrap();
+            storage
+                .sstore(
+                    SpecId::Cancun,
+                    Address::zero(),
+                    &mut AccessList::default(),
+                    &mut gas,
+                    &mut stack,
+                )
+                .unwrap();
+        }
+        assert_eq!(storage.raw_get(U256::zero()), U256::from(3));
+        assert_eq!(storage.original_value(U256::zero()), U256::one());
+        storage.commit();
+        assert_eq!(storage.original_value(U256::zero()), U256::from(3));
+    }
+
+    #[test]
+    fn test_cleared_slots() {
+        let mut storage = Storage::new();
+        storage.raw_set(U256::one(), U256::from(7));
+        let mut gas = Gas::new(100_000);
+        let mut stack = Stack::default();
+        stack.try_push(U256::zero()).unwrap();
+        stack.try_push(U256::one()).unwrap();
+        storage
+            .sstore(
+                SpecId::Cancun,
+                Address::zero(),
+                &mut AccessList::default(),
+                &mut gas,
+                &mut stack,
+            )
+            .unwrap();
+        // the cleared slot is gone, but its original value is kept
+        assert_eq!(storage.iter().count(), 0);
+        assert_eq!(storage.original_value(U256::one()), U256::from(7));
+    }
+
+    #[test]
+    fn test_sstore_sentry() {
+        let store = |spec, gas_limit| {
+            let mut gas = Gas::new(gas_limit);
+            let mut stack = Stack::default();
+            stack.try_push(U256::zero()).unwrap();
+            stack.try_push(U256::zero()).unwrap();
+            let mut access_list = AccessList::default();
+            access_list.add_warm_slot(Address::zero(), U256::zero());
+            Storage::new()
+                .sstore(
+                    spec,
+                    Address::zero(),
+                    &mut access_list,
+                    &mut gas,
+                    &mut stack,
+                )
+                .map(|_| gas.used())
         };
-        let total_gas = static_gas + dynamic_gas;
-        gas.use_gas(total_gas)?;
-        access_list.add_warm_slot(key);
-        Ok(self.raw_set(key, value))
+        assert_eq!(
+            store(SpecId::London, 2300),
+            Err(EvmError::out_of_gas(2301, 2300))
+        );
+        assert_eq!(store(SpecId::London, 2301), Ok(GasCost::WARM_ACCESS));
+        // no sentry before Istanbul
+        assert_eq!(
+            store(SpecId::Petersburg, 5000),
+            Ok(GasCost::SSTORE_RESET_FRONTIER)
+        );
     }
 }
@@ -0,0 +1,608 @@
+use crate::evm::utils::create_address;
+use crate::evm::{
+    precompile, AccessListItem, Address, BlockEnv, Bytecode, DynamicFee, Env, Evm, Fee,
+    GasCategory, GasCost, GasReport, HaltReason, InvalidTransaction, Log, SpecId, TxEnv,
+    WorldState, MAX_INITCODE_SIZE,
+};
+use primitive_types::U256;
+use std::mem;
+
+/// A transaction, the entry point of every execution on chain
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transaction {
+    /// Chain id, read by `CHAINID`
+    pub chain_id: u64,
+    /// Sender of the transaction, who pays for the gas
+    pub caller: Address,
+    /// Recipient, `None` to create a contract running `data` as init code
+    pub to: Option<Address>,
+    /// Value in wei sent to the recipient
+    pub value: U256,
+    /// Calldata, or the init code of a creation
+    pub data: Vec<u8>,
+    /// Maximum gas the transaction can use, intrinsic gas included
+    pub gas_limit: u64,
+    /// Price in wei of every unit of gas, for a legacy transaction
+    pub gas_price: U256,
+    /// Fee caps of an EIP-1559 transaction, which pays the base fee of the block
+    /// plus a tip instead of `gas_price`
+    pub dynamic_fee: Option<DynamicFee>,
+    /// Accounts and storage slots warm from the start, EIP-2930
+    pub access_list: Vec<AccessListItem>,
+}
+
+/// The result of an included transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+    /// `true` if the execution halted with `STOP` or `RETURN`
+    pub status: bool,
+    /// Why the execution stopped
+    pub halt_reason: HaltReason,
+    /// Gas the sender pays for, intrinsic gas included and refunds deducted
+    pub gas_used: GasCost,
+    /// Gas refunded, capped by the fork's refund quotient
+    pub gas_refunded: GasCost,
+    /// What the sender paid for `gas_used`
+    pub fee: Fee,
+    /// Logs emitted, empty unless the execution succeeded
+    pub logs: Vec<Log>,
+    /// Data returned by `RETURN` or `REVERT`
+    pub return_data: Vec<u8>,
+    /// Address of the created contract, for a successful creation
+    pub contract_address: Option<Address>,
+    /// Gas charged per category and per instruction, before refunds
+    pub gas_report: GasReport,
+}
+
+impl Transaction {
+    /// Returns `true` if the transaction creates a contract
+    pub fn is_create(&self) -> bool {
+        self.to.is_none()
+    }
+
+    /// Returns the fee caps of the transaction, a legacy one's gas price caps both
+    pub fn fee_caps(&self) -> DynamicFee {
+        self.dynamic_fee
+            .unwrap_or_else(|| DynamicFee::legacy(self.gas_price))
+    }
+
+    /// Returns the gas charged under `spec` before any code runs
+    ///
+    /// It covers the base cost, every byte of data (EIP-2028), the access list
+    /// (EIP-2930) and, for a creation, every word of init code (EIP-3860).
+    pub fn intrinsic_gas(&self, spec: SpecId) -> GasCost {
+        let mut gas = if self.is_create() && spec.is_enabled_in(SpecId::Homestead) {
+            GasCost::CREATION_TX
+        } else {
+            GasCost::TX
+        };
+        let non_zero_cost = if spec.is_enabled_in(SpecId::Istanbul) {
+            GasCost::TX_DATA_NON_ZERO
+        } else {
+            GasCost::TX_DATA_NON_ZERO_FRONTIER
+        };
+        let zeros = self.data.iter().filter(|&&byte| byte == 0).count() as u64;
+        let non_zeros = self.data.len() as u64 - zeros;
+        gas += GasCost::TX_DATA_ZERO.as_u64() * zeros + non_zero_cost.as_u64() * non_zeros;
+        for item in &self.access_list {
+            gas += GasCost::ACCESS_LIST_ADDRESS
+                + GasCost::ACCESS_LIST_STORAGE_KEY.as_u64() * item.storage_keys.len() as u64;
+        }
+        if self.is_create() && spec.is_enabled_in(SpecId::Shanghai) {
+            gas += GasCost::INITCODE_WORD_COST.per_word(self.data.len());
+        }
+        gas
+    }
+}
+
+/// Executes `tx` on top of `world` in `block`, under the rules of `spec`.
+///
+/// The sender pays for the whole gas limit up front and gets back the gas left
+/// after refunds. The coinbase earns the gas used, except for the base fee
+/// which is burnt from London (EIP-1559).
+/// A failed execution reverts everything but the fee and the sender's nonce.
+/// A call to a precompile runs it with the data as input.
+///
+/// # Returns
+///
+/// Returns the receipt of the transaction.
+/// When the transaction cannot be included, returns `InvalidTransaction` error
+/// and leaves `world` untouched.
+pub fn execute_transaction(
+    world: &mut WorldState,
+    block: &BlockEnv,
+    spec: SpecId,
+    tx: &Transaction,
+) -> Result<Receipt, InvalidTransaction> {
+    let intrinsic_gas = tx.intrinsic_gas(spec);
+    if intrinsic_gas > GasCost::from(tx.gas_limit) {
+        return Err(InvalidTransaction::IntrinsicGasTooLow {
+            intrinsic_gas,
+            gas_limit: tx.gas_limit.into(),
+        });
+    }
+    if tx.is_create() && spec.is_enabled_in(SpecId::Shanghai) && tx.data.len() > MAX_INITCODE_SIZE {
+        return Err(InvalidTransaction::InitCodeSizeExceeded);
+    }
+    let base_fee = if spec.is_enabled_in(SpecId::London) {
+        block.base_fee
+    } else {
+        U256::zero()
+    };
+    let fee_caps = tx.fee_caps();
+    let gas_price = fee_caps.effective_gas_price(base_fee)?;
+    // the balance must cover the gas limit at the max fee, EIP-1559
+    let required = U256::from(tx.gas_limit)
+        .checked_mul(fee_caps.max_fee_per_gas)
+        .and_then(|fee| fee.checked_add(tx.value))
+        .ok_or(InvalidTransaction::FeeOverflow)?;
+    let available = world.balance(tx.caller);
+    if available < required {
+        return Err(InvalidTransaction::InsufficientBalance {
+            required,
+            available,
+        });
+    }
+    let nonce = world.account(tx.caller).map_or(0, |account| account.nonce);
+    if nonce == u64::MAX {
+        return Err(InvalidTransaction::NonceOverflow);
+    }
+    let address = tx.to.unwrap_or_else(|| create_address(tx.caller, nonce));
+    if tx.is_create()
+        && world
+            .account(address)
+            .is_some_and(|account| account.nonce != 0 || !account.code.inner.is_empty())
+    {
+        return Err(InvalidTransaction::CreateCollision);
+    }
+
+    // the fee and the nonce are kept even if the execution fails
+    let sender = world.account_mut(tx.caller);
+    sender.balance -= U256::from(tx.gas_limit) * gas_price;
+    sender.nonce += 1;
+
+    let env = Env {
+        chain_id: tx.chain_id,
+        block: block.clone(),
+        tx: TxEnv {
+            origin: tx.caller,
+            gas_price,
+            caller: tx.caller,
+            address,
+            value: tx.value,
+            access_list: tx.access_list.clone(),
+        },
+    };
+    let mut evm = Evm::new(env, tx.gas_limit).with_spec(spec);
+    // checked against the gas limit above
+    evm.gas
+        .charge([(GasCategory::Intrinsic, intrinsic_gas)])
+        .unwrap();
+    evm.record_gas(None);
+    evm.world = mem::take(world);
+    evm.world.checkpoint();
+    if tx.is_create() {
+        evm.init_contract_account(address);
+        evm.bytecode = Bytecode::from(&tx.data[..]);
+    } else {
+        evm.bytecode = evm.world.code(address);
+        evm.calldata = tx.data.clone();
+    }
+    evm.world.transfer(tx.caller, address, tx.value);
+
+    match tx.to.and_then(|to| precompile(to, spec)) {
+        Some(precompile) => evm.run_precompile(precompile),
+        None => {
+            evm.run_to_halt();
+        }
+    }
+    if tx.is_create() {
+        if let Some(halt_reason) = evm.halt_reason.take() {
+            let output = evm.return_data.clone();
+            evm.halt_reason = Some(evm.finish_create(address, halt_reason, &output));
+        }
+    }
+    let outcome = evm.outcome();
+    if outcome.is_success() {
+        evm.world.discard_checkpoint();
+    } else {
+        evm.world.revert_checkpoint();
+    }
+
+    let gas_used = outcome.gas_used_after_refund();
+    let gas_left = GasCost::from(tx.gas_limit) - gas_used;
+    let fee = Fee {
+        gas_used,
+        gas_price,
+        base_fee,
+    };
+    evm.world.account_mut(tx.caller).balance += U256::from(gas_left.as_u64()) * gas_price;
+    evm.world.account_mut(block.coinbase).balance += fee.tip();
+    *world = evm.world;
+
+    let status = outcome.is_success();
+    Ok(Receipt {
+        status,
+        halt_reason: outcome.halt_reason,
+        gas_used,
+        gas_refunded: outcome.gas_refunded,
+        fee,
+        logs: evm.logs,
+        return_data: outcome.return_data,
+        contract_address: (tx.is_create() && status).then_some(address),
+        gas_report: evm.gas_report,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::utils::address;
+    use crate::evm::{EvmError, OpcodeId};
+
+    const SENDER: u64 = 0x5e;
+    const CONTRACT: u64 = 0xc0;
+
+    /// A world where `SENDER` holds 1 ether and `CONTRACT` runs `code`
+    fn world_with(code: &[u8]) -> WorldState {
+        let mut world = WorldState::new();
+        world.account_mut(address(SENDER)).balance = U256::exp10(18);
+        world.account_mut(address(CONTRACT)).code = Bytecode::from(code);
+        world
+    }
+
+    /// A call from `SENDER` to `CONTRACT` at 10 wei per gas
+    fn call(data: &[u8]) -> Transaction {
+        Transaction {
+            caller: address(SENDER),
+            to: Some(address(CONTRACT)),
+            data: data.to_vec(),
+            gas_limit: 100_000,
+            gas_price: U256::from(10),
+            ..Transaction::default()
+        }
+    }
+
+    fn block() -> BlockEnv {
+        BlockEnv {
+            coinbase: address(0xcb),
+            ..BlockEnv::default()
+        }
+    }
+
+    #[test]
+    fn test_intrinsic_gas() {
+        let mut tx = call(&[]);
+        assert_eq!(tx.intrinsic_gas(SpecId::Cancun), GasCost::TX);
+        tx.data = vec![0, 1, 0, 2];
+        assert_eq!(tx.intrinsic_gas(SpecId::Cancun), GasCost::from(21_040));
+        assert_eq!(tx.intrinsic_gas(SpecId::Petersburg), GasCost::from(21_144));
+        tx.access_list = vec![AccessListItem {
+            address: address(CONTRACT),
+            storage_keys: vec![U256::zero(), U256::one()],
+        }];
+        assert_eq!(tx.intrinsic_gas(SpecId::Cancun), GasCost::from(27_240));
+
+        // 33 bytes of init code are 2 words
+        let tx = Transaction {
+            to: None,
+            data: vec![0; 33],
+            ..Transaction::default()
+        };
+        assert_eq!(tx.intrinsic_gas(SpecId::Shanghai), GasCost::from(53_136));
+        assert_eq!(tx.intrinsic_gas(SpecId::Merge), GasCost::from(53_132));
+        assert_eq!(tx.intrinsic_gas(SpecId::Frontier), GasCost::from(21_132));
+    }
+
+    #[test]
+    fn test_value_transfer() {
+        let mut world = world_with(&[]);
+        let tx = Transaction {
+            value: U256::from(1000),
+            ..call(&[])
+        };
+        let receipt = execute_transaction(&mut world, &block(), SpecId::Cancun, &tx).unwrap();
+        assert!(receipt.status);
+        assert_eq!(receipt.gas_used, GasCost::TX);
+        assert_eq!(world.balance(address(CONTRACT)), U256::from(1000));
+        assert_eq!(world.balance(address(0xcb)), U256::from(210_000));
+        assert_eq!(
+            world.balance(address(SENDER)),
+            U256::exp10(18) - 1000 - 210_000
+        );
+        assert_eq!(world.account(address(SENDER)).unwrap().nonce, 1);
+    }
+
+    #[test]
+    fn test_refund() {
+        // sets slot 0 and clears it again
+        let mut world = world_with(&hex::decode("6001600055600060005500").unwrap());
+        let receipt =
+            execute_transaction(&mut world, &block(), SpecId::Cancun, &call(&[])).unwrap();
+        assert!(receipt.status);
+        // the refund of 19900 is capped at a fifth of the 43212 used
+        assert_eq!(receipt.gas_refunded, GasCost::from(8642));
+        assert_eq!(receipt.gas_used, GasCost::from(43_212 - 8642));
+        assert_eq!(
+            world.balance(address(SENDER)),
+            U256::exp10(18) - U256::from(receipt.gas_used.as_u64() * 10)
+        );
+    }
+
+    #[test]
+    fn test_dynamic_fee() {
+        // returns GASPRICE
+        let mut world = world_with(&hex::decode("3a60005260206000f3").unwrap());
+        let block = BlockEnv {
+            base_fee: U256::from(7),
+            ..block()
+        };
+        let tx = Transaction {
+            dynamic_fee: Some(DynamicFee {
+                max_fee_per_gas: U256::from(20),
+                max_priority_fee_per_gas: U256::from(5),
+            }),
+            ..call(&[])
+        };
+        let receipt = execute_transaction(&mut world, &block, SpecId::Cancun, &tx).unwrap();
+        assert_eq!(U256::from_big_endian(&receipt.return_data), U256::from(12));
+        let fee = receipt.fee;
+        assert_eq!(fee.gas_price, U256::from(12));
+        assert_eq!(fee.total(), U256::from(fee.gas_used.as_u64() * 12));
+        // the base fee is burnt, the coinbase earns the tip
+        assert_eq!(fee.burnt(), U256::from(fee.gas_used.as_u64() * 7));
+        assert_eq!(world.balance(address(0xcb)), fee.tip());
+        assert_eq!(
+            world.balance(address(SENDER)),
+            U256::exp10(18) - fee.total()
+        );
+
+        // no base fee before London
+        let mut world = world_with(&[]);
+        let receipt = execute_transaction(&mut world, &block, SpecId::Berlin, &tx).unwrap();
+        assert_eq!(receipt.fee.gas_price, U256::from(5));
+        assert_eq!(world.balance(address(0xcb)), receipt.fee.total());
+    }
+
+    #[test]
+    fn test_gas_report() {
+        // sets slot 0 and clears it again
+        let mut world = world_with(&hex::decode("6001600055600060005500").unwrap());
+        let receipt =
+            execute_transaction(&mut world, &block(), SpecId::Cancun, &call(&[])).unwrap();
+        let total = receipt.gas_report.total();
+        assert_eq!(total.get(GasCategory::Intrinsic), GasCost::TX);
+        assert_eq!(total.get(GasCategory::Base), GasCost::from(12));
+        assert_eq!(total.get(GasCategory::ColdAccess), GasCost::COLD_SLOAD);
+        assert_eq!(total.get(GasCategory::StorageWrite), GasCost::from(20_100));
+        assert_eq!(total.total(), receipt.gas_used + receipt.gas_refunded);
+        assert_eq!(total.refunded(), 19_900);
+
+        // the first SSTORE sets the cold slot, the second clears it
+        let first = receipt.gas_report.at_pc(4);
+        assert_eq!(first.get(GasCategory::ColdAccess), GasCost::COLD_SLOAD);
+        assert_eq!(first.get(GasCategory::StorageWrite), GasCost::from(20_000));
+        let second = receipt.gas_report.at_pc(9);
+        assert_eq!(second.get(GasCategory::ColdAccess), GasCost::ZERO);
+        assert_eq!(second.get(GasCategory::StorageWrite), GasCost::WARM_ACCESS);
+        assert_eq!(second.refunded(), 19_900);
+    }
+
+    #[test]
+    fn test_failure_keeps_fee_and_nonce() {
+        // writes slot 0, logs, then reverts
+        let mut world = world_with(&hex::decode("600160005560006000a060006000fd").unwrap());
+        let tx = Transaction {
+            value: U256::from(1000),
+            ..call(&[])
+        };
+        let receipt = execute_transaction(&mut world, &block(), SpecId::Cancun, &tx).unwrap();
+        assert!(!receipt.status);
+        assert_eq!(receipt.halt_reason, HaltReason::Revert);
+        assert!(receipt.logs.is_empty());
+        assert_eq!(receipt.gas_refunded, GasCost::ZERO);
+        let contract = world.account(address(CONTRACT)).unwrap();
+        assert_eq!(contract.storage.raw_get(U256::zero()), U256::zero());
+        assert_eq!(contract.balance, U256::zero());
+        assert_eq!(world.account(address(SENDER)).unwrap().nonce, 1);
+        assert_eq!(
+            world.balance(address(SENDER)),
+            U256::exp10(18) - U256::from(receipt.gas_used.as_u64() * 10)
+        );
+
+        // an exceptional halt consumes the whole gas limit
+        let mut world = world_with(&[OpcodeId::ADD.as_u8()]);
+        let receipt =
+            execute_transaction(&mut world, &block(), SpecId::Cancun, &call(&[])).unwrap();
+        assert_eq!(
+            receipt.halt_reason.error(),
+            Some(&EvmError::StackUnderflow {
+                required: 2,
+                available: 0
+            })
+        );
+        assert_eq!(receipt.gas_used, GasCost::from(100_000));
+    }
+
+    #[test]
+    fn test_logs() {
+        // LOG1 of the calldata size as topic
+        let mut world = world_with(&hex::decode("3660006000a1").unwrap());
+        let receipt =
+            execute_transaction(&mut world, &block(), SpecId::Cancun, &call(&[1, 2])).unwrap();
+        assert_eq!(
+            receipt.logs,
+            vec![Log {
+                address: address(CONTRACT),
+                topics: vec![U256::from(2)],
+                data: Vec::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_create() {
+        // init code returning the 1-byte runtime `STOP`
+        let init_code = hex::decode("60016000f3").unwrap();
+        let mut world = world_with(&[]);
+        let tx = Transaction {
+            to: None,
+            data: init_code.clone(),
+            ..call(&[])
+        };
+        let receipt = execute_transaction(&mut world, &block(), SpecId::Cancun, &tx).unwrap();
+        let expected = create_address(address(SENDER), 0);
+        assert_eq!(receipt.contract_address, Some(expected));
+        let contract = world.account(expected).unwrap();
+        assert_eq!(contract.code.to_bytes(), vec![0]);
+        assert_eq!(contract.nonce, 1);
+        // the code deposit is put down to the RETURN
+        let deposit = GasCost::CODE_DEPOSIT_BYTE_COST;
+        let charges = receipt.gas_report.at_pc(4);
+        assert_eq!(charges.get(GasCategory::Dynamic), deposit);
+        assert_eq!(charges.get(GasCategory::Memory), GasCost::from(3));
+        // intrinsic gas, two pushes, memory expansion and the code deposit
+        let intrinsic = tx.intrinsic_gas(SpecId::Cancun).as_u64();
+        assert_eq!(
+            receipt.gas_used,
+            GasCost::from(intrinsic + 6 + 3 + GasCost::CODE_DEPOSIT_BYTE_COST.as_u64())
+        );
+
+        // the deposit does not fit in the gas limit
+        let mut world = world_with(&[]);
+        let tx = Transaction {
+            gas_limit: intrinsic + 9 + 199,
+            ..tx
+        };
+        let receipt = execute_transaction(&mut world, &block(), SpecId::Cancun, &tx).unwrap();
+        assert!(!receipt.status);
+        assert_eq!(receipt.contract_address, None);
+        assert!(world.account(expected).is_none());
+    }
+
+    #[test]
+    fn test_call_precompile() {
+        // the identity precompile returns its input
+        let mut world = world_with(&[]);
+        let tx = Transaction {
+            to: Some(address(0x04)),
+            value: U256::from(1000),
+            ..call(&[1, 2, 3])
+        };
+        let receipt = execute_transaction(&mut world, &block(), SpecId::Cancun, &tx).unwrap();
+        assert!(receipt.status);
+        assert_eq!(receipt.halt_reason, HaltReason::Return);
+        assert_eq!(receipt.return_data, vec![1, 2, 3]);
+        let intrinsic = tx.intrinsic_gas(SpecId::Cancun);
+        assert_eq!(receipt.gas_used, intrinsic + 15 + 3);
+        assert_eq!(world.balance(address(0x04)), U256::from(1000));
+
+        // a point off the curve fails, consuming all the gas and reverting the transfer
+        let mut input = vec![0; 64];
+        input[31] = 1;
+        input[63] = 3;
+        let tx = Transaction {
+            to: Some(address(0x06)),
+            data: input,
+            ..tx
+        };
+        let receipt = execute_transaction(&mut world, &block(), SpecId::Cancun, &tx).unwrap();
+        assert!(!receipt.status);
+        assert_eq!(
+            receipt.halt_reason.error(),
+            Some(&EvmError::PrecompileFailure)
+        );
+        assert_eq!(receipt.gas_used, GasCost::from(tx.gas_limit));
+        assert!(world.is_empty(address(0x06)));
+    }
+
+    #[test]
+    fn test_invalid_transactions() {
+        let mut world = world_with(&[]);
+        let before = world.clone();
+        let tx = Transaction {
+            gas_limit: 20_999,
+            ..call(&[])
+        };
+        assert_eq!(
+            execute_transaction(&mut world, &block(), SpecId::Cancun, &tx),
+            Err(InvalidTransaction::IntrinsicGasTooLow {
+                intrinsic_gas: GasCost::TX,
+                gas_limit: GasCost::from(20_999)
+            })
+        );
+        let tx = Transaction {
+            value: U256::exp10(18),
+            ..call(&[])
+        };
+        assert_eq!(
+            execute_transaction(&mut world, &block(), SpecId::Cancun, &tx),
+            Err(InvalidTransaction::InsufficientBalance {
+                required: U256::exp10(18) + 1_000_000,
+                available: U256::exp10(18)
+            })
+        );
+        let tx = Transaction {
+            to: None,
+            data: vec![0; MAX_INITCODE_SIZE + 1],
+            gas_limit: 1_000_000,
+            ..call(&[])
+        };
+        assert_eq!(
+            execute_transaction(&mut world, &block(), SpecId::Cancun, &tx),
+            Err(InvalidTransaction::InitCodeSizeExceeded)
+        );
+        let block = BlockEnv {
+            base_fee: U256::from(11),
+            ..block()
+        };
+        assert_eq!(
+            execute_transaction(&mut world, &block, SpecId::Cancun, &call(&[])),
+            Err(InvalidTransaction::FeeCapTooLow {
+                max_fee_per_gas: U256::from(10),
+                base_fee: U256::from(11)
+            })
+        );
+        // the balance must cover the gas limit at the max fee
+        let tx = Transaction {
+            dynamic_fee: Some(DynamicFee {
+                max_fee_per_gas: U256::exp10(13) + 1,
+                max_priority_fee_per_gas: U256::zero(),
+            }),
+            ..call(&[])
+        };
+        assert_eq!(
+            execute_transaction(&mut world, &block, SpecId::Cancun, &tx),
+            Err(InvalidTransaction::InsufficientBalance {
+                required: U256::exp10(18) + 100_000,
+                available: U256::exp10(18)
+            })
+        );
+        assert_eq!(world, before);
+
+        // a fee which does not fit in 256 bits, however rich the sender
+        world.account_mut(address(SENDER)).balance = U256::MAX;
+        let tx = Transaction {
+            dynamic_fee: Some(DynamicFee {
+                max_fee_per_gas: U256::MAX / 2,
+                max_priority_fee_per_gas: U256::zero(),
+            }),
+            ..call(&[])
+        };
+        assert_eq!(
+            execute_transaction(&mut world, &block, SpecId::Cancun, &tx),
+            Err(InvalidTransaction::FeeOverflow)
+        );
+        // a sender whose nonce cannot be incremented
+        world.account_mut(address(SENDER)).nonce = u64::MAX;
+        let tx = Transaction {
+            gas_price: U256::from(11),
+            ..call(&[])
+        };
+        assert_eq!(
+            execute_transaction(&mut world, &block, SpecId::Cancun, &tx),
+            Err(InvalidTransaction::NonceOverflow)
+        );
+        assert_eq!(world.balance(address(SENDER)), U256::MAX);
+    }
+}
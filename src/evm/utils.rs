@@ -1,6 +1,79 @@
-use primitive_types::U256;
+use primitive_types::{H160, U256};
+use sha3::{Digest, Keccak256};
 use std::cmp::Ordering;
 
+/// Computes the Keccak-256 hash of the given bytes
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+/// Converts an address to a stack word, left padded with zeros
+pub fn address_to_word(address: H160) -> U256 {
+    U256::from_big_endian(address.as_bytes())
+}
+
+/// Converts a stack word to an address, keeping the low 20 bytes
+pub fn word_to_address(word: U256) -> H160 {
+    let mut bytes = [0u8; 32];
+    word.to_big_endian(&mut bytes);
+    H160::from_slice(&bytes[12..])
+}
+
+/// The address holding the number `n`, for tests
+#[cfg(test)]
+pub(crate) fn address(n: u64) -> H160 {
+    word_to_address(U256::from(n))
+}
+
+/// Computes the address of a contract created by `CREATE`,
+/// `keccak256(rlp([sender, nonce]))[12..]`
+pub fn create_address(sender: H160, nonce: u64) -> H160 {
+    // rlp of the nonce, a single byte below 0x80 or a length-prefixed big endian integer
+    let nonce_bytes = nonce.to_be_bytes();
+    let nonce_bytes = &nonce_bytes[nonce.leading_zeros() as usize / 8..];
+    let mut rlp_nonce = Vec::with_capacity(9);
+    match nonce_bytes {
+        [byte] if *byte < 0x80 => rlp_nonce.push(*byte),
+        _ => {
+            rlp_nonce.push(0x80 + nonce_bytes.len() as u8);
+            rlp_nonce.extend_from_slice(nonce_bytes);
+        }
+    }
+    // the whole list is shorter than 56 bytes, so the prefix is a single byte
+    let mut rlp = Vec::with_capacity(31);
+    rlp.push(0xc0 + 21 + rlp_nonce.len() as u8);
+    rlp.push(0x80 + 20);
+    rlp.extend_from_slice(sender.as_bytes());
+    rlp.extend_from_slice(&rlp_nonce);
+    H160::from_slice(&keccak256(&rlp)[12..])
+}
+
+/// Computes the address of a contract created by `CREATE2` (EIP-1014),
+/// `keccak256(0xff ++ sender ++ salt ++ keccak256(init_code))[12..]`
+pub fn create2_address(sender: H160, salt: U256, init_code: &[u8]) -> H160 {
+    let mut preimage = Vec::with_capacity(85);
+    preimage.push(0xff);
+    preimage.extend_from_slice(sender.as_bytes());
+    let mut salt_bytes = [0u8; 32];
+    salt.to_big_endian(&mut salt_bytes);
+    preimage.extend_from_slice(&salt_bytes);
+    preimage.extend_from_slice(&keccak256(init_code));
+    H160::from_slice(&keccak256(&preimage)[12..])
+}
+
+/// Fills `dest` with bytes of `source` starting at `offset`,
+/// bytes past the end of `source` are zero.
+pub fn copy_padded(dest: &mut [u8], source: &[u8], offset: U256) {
+    let offset = if offset > U256::from(source.len()) {
+        source.len()
+    } else {
+        offset.as_usize()
+    };
+    let available = (source.len() - offset).min(dest.len());
+    dest[..available].copy_from_slice(&source[offset..offset + available]);
+    dest[available..].fill(0);
+}
+
 pub struct SignWrapper<T>(pub T);
 
 pub trait SignExt: Sized + Copy + Ord {
@@ -59,16 +132,6 @@ impl<T: SignExt> PartialOrd<Self> for SignWrapper<T> {
     }
 }
 
-impl<T: SignExt> SignWrapper<T> {
-    pub fn is_neg(&self) -> bool {
-        self.0.is_neg()
-    }
-
-    pub fn neg(&self) -> Self {
-        SignWrapper(self.0.neg())
-    }
-}
-
 impl SignExt for U256 {
     fn is_neg(&self) -> bool {
         127 < self.byte(31)
@@ -89,39 +152,122 @@ mod tests {
     use std::str::FromStr;
 
     #[test]
-    fn test_is_neg() {
-        assert_eq!(U256::from(0).is_neg(), false);
-        assert_eq!(U256::from(1).is_neg(), false);
-        assert_eq!(U256::from(127).is_neg(), false);
-        assert_eq!(U256::from(128).is_neg(), false);
-        assert_eq!(U256::from(255).is_neg(), false);
-        assert_eq!(U256::from(256).is_neg(), false);
-        assert_eq!(
-            U256::from_str("0x7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff")
-                .unwrap()
-                .is_neg(),
-            false
-        );
+    fn test_keccak256() {
         assert_eq!(
-            U256::from_str("0x8000000000000000000000000000000000000000000000000000000000000000")
-                .unwrap()
-                .is_neg(),
-            true
+            hex::encode(keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
         );
         assert_eq!(
-            U256::from_str("0x8000000000000000000000000000000000000000000000000000000000000001")
-                .unwrap()
-                .is_neg(),
-            true
+            hex::encode(keccak256(b"abc")),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
         );
         assert_eq!(
-            U256::from_str("0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff")
-                .unwrap()
-                .is_neg(),
-            true
+            hex::encode(keccak256(&[0u8; 32])),
+            "290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563"
         );
     }
 
+    fn address(s: &str) -> H160 {
+        H160::from_slice(&hex::decode(s).unwrap())
+    }
+
+    #[test]
+    fn test_create_address() {
+        let sender = address("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+        for (nonce, expected) in [
+            (0, "cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"),
+            (1, "343c43a37d37dff08ae8c4a11544c718abb4fcf8"),
+            (2, "f778b86fa74e846c4f0a1fbd1335fe81c00a0c91"),
+            (3, "fffd933a0bc612844eaf0c6fe3e5b8e9b6c1d19c"),
+        ] {
+            assert_eq!(create_address(sender, nonce), address(expected));
+        }
+    }
+
+    #[test]
+    fn test_create2_address() {
+        // examples from EIP-1014
+        let cases: [(&str, U256, &[u8], &str); 4] = [
+            (
+                "0000000000000000000000000000000000000000",
+                U256::zero(),
+                &[0x00],
+                "4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38",
+            ),
+            (
+                "deadbeef00000000000000000000000000000000",
+                U256::zero(),
+                &[0x00],
+                "b928f69bb1d91cd65274e3c79d8986362984fda3",
+            ),
+            (
+                "00000000000000000000000000000000deadbeef",
+                U256::from(0xcafebabe_u64),
+                &[0xde, 0xad, 0xbe, 0xef],
+                "60f3f640a8508fc6a86d45df051962668e1e8ac7",
+            ),
+            (
+                "0000000000000000000000000000000000000000",
+                U256::zero(),
+                &[],
+                "e33c0c7f7df4809055c3eba6c09cfe4baf1bd9e0",
+            ),
+        ];
+        for (sender, salt, init_code, expected) in cases {
+            assert_eq!(
+                create2_address(address(sender), salt, init_code),
+                address(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn test_copy_padded() {
+        let source = [1u8, 2, 3];
+        let mut dest = [0xffu8; 4];
+        copy_padded(&mut dest, &source, U256::zero());
+        assert_eq!(dest, [1, 2, 3, 0]);
+        copy_padded(&mut dest, &source, U256::from(2));
+        assert_eq!(dest, [3, 0, 0, 0]);
+        copy_padded(&mut dest, &source, U256::from(3));
+        assert_eq!(dest, [0, 0, 0, 0]);
+        let mut dest = [0xffu8; 2];
+        copy_padded(&mut dest, &source, !U256::zero());
+        assert_eq!(dest, [0, 0]);
+        copy_padded(&mut dest, &source, U256::one());
+        assert_eq!(dest, [2, 3]);
+    }
+
+    #[test]
+    fn test_is_neg() {
+        assert!(!U256::from(0).is_neg());
+        assert!(!U256::from(1).is_neg());
+        assert!(!U256::from(127).is_neg());
+        assert!(!U256::from(128).is_neg());
+        assert!(!U256::from(255).is_neg());
+        assert!(!U256::from(256).is_neg());
+        assert!(!U256::from_str(
+            "0x7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
+        )
+        .unwrap()
+        .is_neg());
+        assert!(U256::from_str(
+            "0x8000000000000000000000000000000000000000000000000000000000000000"
+        )
+        .unwrap()
+        .is_neg());
+        assert!(U256::from_str(
+            "0x8000000000000000000000000000000000000000000000000000000000000001"
+        )
+        .unwrap()
+        .is_neg());
+        assert!(U256::from_str(
+            "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
+        )
+        .unwrap()
+        .is_neg());
+    }
+
     #[test]
     fn test_signed_cmp() {
         let neg_big =
@@ -0,0 +1,403 @@
+use crate::evm::{
+    AccessList, Address, Bytecode, EvmError, Gas, SpecId, Stack, Storage, TransientStorage,
+};
+use primitive_types::U256;
+use std::collections::HashMap;
+use std::mem;
+
+/// An account in the world state
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Account {
+    /// Balance in wei
+    pub balance: U256,
+    /// Number of transactions sent, or contracts created by a contract
+    pub nonce: u64,
+    /// Code of the account, empty for externally owned accounts
+    pub code: Bytecode,
+    /// Persistent storage
+    pub storage: Storage,
+    /// Transient storage, cleared between transactions
+    pub transient_storage: TransientStorage,
+}
+
+impl Account {
+    /// Returns `true` if the account is empty as defined by EIP-161,
+    /// i.e. no code, zero nonce and zero balance.
+    pub fn is_empty(&self) -> bool {
+        self.code.inner.is_empty() && self.nonce == 0 && self.balance.is_zero()
+    }
+}
+
+/// A single change of the world state, with the value to restore when it is reverted
+#[derive(Debug, Clone)]
+enum JournalEntry {
+    /// The account did not exist
+    Created(Address),
+    Balance(Address, U256),
+    Nonce(Address, u64),
+    Code(Address, Bytecode),
+    Storage(Address, U256, U256),
+    TransientStorage(Address, U256, U256),
+}
+
+/// All the accounts, keyed by address
+#[derive(Debug, Clone, Default)]
+pub struct WorldState {
+    accounts: HashMap<Address, Account>,
+    /// Changes since the first open checkpoint
+    journal: Vec<JournalEntry>,
+    /// Length of the journal when each open checkpoint was taken
+    checkpoints: Vec<usize>,
+}
+
+impl PartialEq for WorldState {
+    fn eq(&self, other: &Self) -> bool {
+        self.accounts == other.accounts
+    }
+}
+
+impl Eq for WorldState {}
+
+impl WorldState {
+    pub fn new() -> Self {
+        WorldState::default()
+    }
+
+    /// Starts recording the changes made from now on,
+    /// so they can be reverted with [`WorldState::revert_checkpoint`]
+    pub fn checkpoint(&mut self) {
+        self.checkpoints.push(self.journal.len());
+    }
+
+    /// Undoes the changes since the last checkpoint and closes it
+    pub fn revert_checkpoint(&mut self) {
+        let journal_len = self
+            .checkpoints
+            .pop()
+            .expect("revert_checkpoint called without a checkpoint");
+        for entry in self.journal.split_off(journal_len).into_iter().rev() {
+            self.undo(entry);
+        }
+    }
+
+    /// Keeps the changes since the last checkpoint and closes it,
+    /// they are reverted with the enclosing checkpoint if any
+    pub fn discard_checkpoint(&mut self) {
+        self.checkpoints
+            .pop()
+            .expect("discard_checkpoint called without a checkpoint");
+        if self.checkpoints.is_empty() {
+            self.journal.clear();
+        }
+    }
+
+    /// Records a change, if a checkpoint is open
+    fn record(&mut self, entry: JournalEntry) {
+        if !self.checkpoints.is_empty() {
+            self.journal.push(entry);
+        }
+    }
+
+    fn undo(&mut self, entry: JournalEntry) {
+        let address = match entry {
+            JournalEntry::Created(address) => {
+                self.accounts.remove(&address);
+                return;
+            }
+            JournalEntry::Balance(address, _)
+            | JournalEntry::Nonce(address, _)
+            | JournalEntry::Code(address, _)
+            | JournalEntry::Storage(address, _, _)
+            | JournalEntry::TransientStorage(address, _, _) => address,
+        };
+        // changed accounts exist until their creation is undone
+        let account = self.accounts.get_mut(&address).unwrap();
+        match entry {
+            JournalEntry::Created(_) => unreachable!(),
+            JournalEntry::Balance(_, balance) => account.balance = balance,
+            JournalEntry::Nonce(_, nonce) => account.nonce = nonce,
+            JournalEntry::Code(_, code) => account.code = code,
+            JournalEntry::Storage(_, key, value) => {
+                account.storage.raw_set(key, value);
+            }
+            JournalEntry::TransientStorage(_, key, value) => {
+                account.transient_storage.raw_set(key, value);
+            }
+        }
+    }
+
+    /// Returns the account at `address` to change, recording its creation if it does not exist
+    fn touch(&mut self, address: Address) -> &mut Account {
+        if !self.accounts.contains_key(&address) {
+            self.record(JournalEntry::Created(address));
+        }
+        self.accounts.entry(address).or_default()
+    }
+
+    /// Returns the account at `address`, if it exists
+    pub fn account(&self, address: Address) -> Option<&Account> {
+        self.accounts.get(&address)
+    }
+
+    /// Returns the account at `address`, creating an empty one if it does not exist.
+    ///
+    /// Changes through it are not journaled, it is meant to set up the state
+    /// outside of a checkpoint.
+    pub fn account_mut(&mut self, address: Address) -> &mut Account {
+        self.accounts.entry(address).or_default()
+    }
+
+    /// Insert or replace the account at `address`, without journaling it
+    pub fn insert_account(&mut self, address: Address, account: Account) {
+        self.accounts.insert(address, account);
+    }
+
+    /// Sets the nonce of `address`
+    pub fn set_nonce(&mut self, address: Address, nonce: u64) {
+        let previous = mem::replace(&mut self.touch(address).nonce, nonce);
+        self.record(JournalEntry::Nonce(address, previous));
+    }
+
+    /// Sets the code of `address`
+    pub fn set_code(&mut self, address: Address, code: Bytecode) {
+        let previous = mem::replace(&mut self.touch(address).code, code);
+        self.record(JournalEntry::Code(address, previous));
+    }
+
+    fn set_balance(&mut self, address: Address, balance: U256) {
+        let previous = mem::replace(&mut self.touch(address).balance, balance);
+        self.record(JournalEntry::Balance(address, previous));
+    }
+
+    /// Implementation of the SLOAD opcode on the storage of `address`, see [`Storage::sload`]
+    pub fn sload(
+        &self,
+        spec: SpecId,
+        address: Address,
+        access_list: &mut AccessList,
+        gas: &mut Gas,
+        stack: &mut Stack,
+    ) -> Result<U256, EvmError> {
+        let empty = Storage::new();
+        let storage = self
+            .account(address)
+            .map_or(&empty, |account| &account.storage);
+        storage.sload(spec, address, access_list, gas, stack)
+    }
+
+    /// Implementation of the SSTORE opcode on the storage of `address`, see [`Storage::sstore`]
+    pub fn sstore(
+        &mut self,
+        spec: SpecId,
+        address: Address,
+        access_list: &mut AccessList,
+        gas: &mut Gas,
+        stack: &mut Stack,
+    ) -> Result<U256, EvmError> {
+        let storage = &mut self.touch(address).storage;
+        let (key, previous) = storage.sstore(spec, address, access_list, gas, stack)?;
+        self.record(JournalEntry::Storage(address, key, previous));
+        Ok(previous)
+    }
+
+    /// Implementation of the TLOAD opcode on the transient storage of `address`,
+    /// see [`TransientStorage::tload`]
+    pub fn tload(
+        &self,
+        address: Address,
+        gas: &mut Gas,
+        stack: &mut Stack,
+    ) -> Result<U256, EvmError> {
+        let empty = TransientStorage::new();
+        let transient_storage = self
+            .account(address)
+            .map_or(&empty, |account| &account.transient_storage);
+        transient_storage.tload(gas, stack)
+    }
+
+    /// Implementation of the TSTORE opcode on the transient storage of `address`,
+    /// see [`TransientStorage::tstore`]
+    pub fn tstore(
+        &mut self,
+        address: Address,
+        gas: &mut Gas,
+        stack: &mut Stack,
+    ) -> Result<U256, EvmError> {
+        let transient_storage = &mut self.touch(address).transient_storage;
+        let (key, previous) = transient_storage.tstore(gas, stack)?;
+        self.record(JournalEntry::TransientStorage(address, key, previous));
+        Ok(previous)
+    }
+
+    /// Returns `true` if the account does not exist or is empty
+    pub fn is_empty(&self, address: Address) -> bool {
+        self.account(address).is_none_or(Account::is_empty)
+    }
+
+    /// Returns the balance of `address`, zero if the account does not exist
+    pub fn balance(&self, address: Address) -> U256 {
+        self.account(address)
+            .map(|account| account.balance)
+            .unwrap_or_default()
+    }
+
+    /// Returns the code of `address`, empty if the account does not exist
+    pub fn code(&self, address: Address) -> Bytecode {
+        self.account(address)
+            .map(|account| account.code.clone())
+            .unwrap_or_default()
+    }
+
+    /// Move `value` wei from `from` to `to`
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` without changing anything if `from` has insufficient balance.
+    pub fn transfer(&mut self, from: Address, to: Address, value: U256) -> bool {
+        if self.balance(from) < value {
+            return false;
+        }
+        if value.is_zero() || from == to {
+            return true;
+        }
+        self.set_balance(from, self.balance(from) - value);
+        self.set_balance(to, self.balance(to) + value);
+        true
+    }
+
+    /// Commit the storage of every account, called between transactions,
+    /// so the current values are the original values of the next transaction
+    pub fn commit(&mut self) {
+        for account in self.accounts.values_mut() {
+            account.storage.commit();
+        }
+    }
+
+    /// Clear the transient storage of every account, called between transactions
+    pub fn clear_transient_storage(&mut self) {
+        for account in self.accounts.values_mut() {
+            account.transient_storage.clear();
+        }
+    }
+
+    /// Iterate over the accounts, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&Address, &Account)> {
+        self.accounts.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer() {
+        let alice = Address::repeat_byte(0x01);
+        let bob = Address::repeat_byte(0x02);
+        let mut world = WorldState::new();
+        world.account_mut(alice).balance = U256::from(100);
+
+        assert!(world.transfer(alice, bob, U256::from(30)));
+        assert_eq!(world.balance(alice), U256::from(70));
+        assert_eq!(world.balance(bob), U256::from(30));
+
+        assert!(!world.transfer(alice, bob, U256::from(71)));
+        assert_eq!(world.balance(alice), U256::from(70));
+        assert_eq!(world.balance(bob), U256::from(30));
+
+        // an empty transfer does not create the account
+        assert!(world.transfer(alice, Address::zero(), U256::zero()));
+        assert!(world.account(Address::zero()).is_none());
+        assert!(world.is_empty(Address::zero()));
+        assert!(!world.is_empty(bob));
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let alice = Address::repeat_byte(0x01);
+        let bob = Address::repeat_byte(0x02);
+        let carol = Address::repeat_byte(0x03);
+        let mut world = WorldState::new();
+        world.account_mut(alice).balance = U256::from(100);
+        let before = world.clone();
+
+        world.checkpoint();
+        world.transfer(alice, bob, U256::from(10));
+        world.checkpoint();
+        world.transfer(alice, carol, U256::from(20));
+        world.transfer(bob, carol, U256::from(5));
+        world.revert_checkpoint();
+        assert_eq!(world.balance(alice), U256::from(90));
+        assert_eq!(world.balance(bob), U256::from(10));
+        assert!(world.account(carol).is_none());
+
+        // kept changes are reverted with the enclosing checkpoint
+        world.checkpoint();
+        world.transfer(bob, carol, U256::from(5));
+        world.discard_checkpoint();
+        assert_eq!(world.balance(carol), U256::from(5));
+        world.revert_checkpoint();
+        assert_eq!(world, before);
+        assert!(world.account(bob).is_none());
+        assert!(world.journal.is_empty());
+
+        world.checkpoint();
+        world.transfer(alice, bob, U256::from(10));
+        world.discard_checkpoint();
+        assert_eq!(world.balance(bob), U256::from(10));
+        assert!(world.journal.is_empty());
+    }
+
+    #[test]
+    fn test_checkpoint_storage() {
+        let alice = Address::repeat_byte(0x01);
+        let mut world = WorldState::new();
+        let mut access_list = AccessList::new();
+        let mut gas = Gas::new(100_000);
+        let mut stack = Stack::new();
+        world.checkpoint();
+
+        // reading neither creates the account nor journals anything
+        stack.try_push(U256::one()).unwrap();
+        world
+            .sload(
+                SpecId::Cancun,
+                alice,
+                &mut access_list,
+                &mut gas,
+                &mut stack,
+            )
+            .unwrap();
+        stack.try_push(U256::one()).unwrap();
+        world.tload(alice, &mut gas, &mut stack).unwrap();
+        assert!(world.account(alice).is_none());
+        assert!(world.journal.is_empty());
+
+        // writing journals the slots
+        for value in [2u64, 3] {
+            stack.try_push(U256::from(value)).unwrap();
+            stack.try_push(U256::one()).unwrap();
+            world
+                .sstore(
+                    SpecId::Cancun,
+                    alice,
+                    &mut access_list,
+                    &mut gas,
+                    &mut stack,
+                )
+                .unwrap();
+            stack.try_push(U256::from(value)).unwrap();
+            stack.try_push(U256::one()).unwrap();
+            world.tstore(alice, &mut gas, &mut stack).unwrap();
+        }
+        assert_eq!(world.journal.len(), 5);
+        let account = world.account(alice).unwrap();
+        assert_eq!(account.storage.raw_get(U256::one()), U256::from(3));
+        assert_eq!(
+            account.transient_storage.raw_get(U256::one()),
+            U256::from(3)
+        );
+        world.revert_checkpoint();
+        assert!(world.account(alice).is_none());
+    }
+}